@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE weather_info ADD COLUMN provider VARCHAR(32);
@@ -0,0 +1,26 @@
+-- Add migration script here
+CREATE TABLE weather_daily (
+    id uuid PRIMARY KEY,
+    user_id uuid REFERENCES users (user_id),
+    -- 地理信息
+    latitude FLOAT NOT NULL,
+    longitude FLOAT NOT NULL,
+    city_name VARCHAR(100),
+    provider VARCHAR(32),
+    -- 温度相关指标
+    temperature_min FLOAT,
+    temperature_max FLOAT,
+    -- 日出日落
+    sunrise_time TIMESTAMP,
+    sunset_time TIMESTAMP,
+    -- 降水相关指标
+    precipitation_accumulation FLOAT,
+    -- 天气代码
+    weather_code INTEGER,
+    -- 时间
+    forecast_time TIMESTAMP NOT NULL,
+    -- 元数据,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, forecast_time, latitude, longitude)
+);
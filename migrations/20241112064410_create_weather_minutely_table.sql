@@ -0,0 +1,21 @@
+-- Add migration script here
+CREATE TABLE weather_minutely (
+    id uuid PRIMARY KEY,
+    user_id uuid REFERENCES users (user_id),
+    -- 地理信息
+    latitude FLOAT NOT NULL,
+    longitude FLOAT NOT NULL,
+    city_name VARCHAR(100),
+    provider VARCHAR(32),
+    -- 降水相关指标
+    precipitation_intensity FLOAT NOT NULL,
+    precipitation_type VARCHAR(16),
+    precipitation_probability FLOAT,
+    -- 时间
+    forecast_time TIMESTAMP NOT NULL,
+    -- 元数据,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, forecast_time, latitude, longitude)
+);
+-- 分钟级数据只短期保留, 按时间清理
+CREATE INDEX weather_minutely_forecast_time_idx ON weather_minutely (forecast_time);
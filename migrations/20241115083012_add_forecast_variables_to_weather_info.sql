@@ -0,0 +1,21 @@
+-- Add migration script here
+ALTER TABLE weather_info
+    -- 降水相关指标
+    ADD COLUMN rain_intensity FLOAT,
+    ADD COLUMN freezing_rain_intensity FLOAT,
+    -- 温湿度相关指标
+    ADD COLUMN dew_point FLOAT,
+    ADD COLUMN humidity FLOAT,
+    -- 风相关指标
+    ADD COLUMN wind_direction FLOAT,
+    ADD COLUMN wind_gust FLOAT,
+    -- 气压, 紫外线, 能见度
+    ADD COLUMN pressure_surface_level FLOAT,
+    ADD COLUMN uv_index FLOAT,
+    ADD COLUMN visibility FLOAT,
+    -- 云相关指标
+    ADD COLUMN cloud_cover FLOAT,
+    ADD COLUMN cloud_base FLOAT,
+    ADD COLUMN cloud_ceiling FLOAT,
+    -- 天气代码
+    ADD COLUMN weather_code INTEGER;
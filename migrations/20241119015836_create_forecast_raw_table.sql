@@ -0,0 +1,19 @@
+-- Add migration script here
+CREATE TABLE forecast_raw (
+    id uuid PRIMARY KEY,
+    user_id uuid REFERENCES users (user_id),
+    provider VARCHAR(32) NOT NULL,
+    -- 地理信息
+    latitude FLOAT NOT NULL,
+    longitude FLOAT NOT NULL,
+    city_name VARCHAR(100),
+    -- 请求信息
+    http_status SMALLINT NOT NULL,
+    requested_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    -- gzip 压缩后的原始 JSON
+    payload BYTEA NOT NULL,
+    -- 元数据,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX forecast_raw_requested_at_idx ON forecast_raw (requested_at);
+CREATE INDEX forecast_raw_location_idx ON forecast_raw (latitude, longitude);
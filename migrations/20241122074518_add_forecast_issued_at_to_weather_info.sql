@@ -0,0 +1,31 @@
+-- Add migration script here
+-- 每次发布的预报都保留, 不再覆盖同一小时的旧预报
+ALTER TABLE weather_info ADD COLUMN forecast_issued_at TIMESTAMP WITH TIME ZONE;
+-- 旧数据只保留了最后一次写入, 以其更新时间作为发布时间
+UPDATE weather_info
+    SET forecast_issued_at = COALESCE(updated_at, created_at, CURRENT_TIMESTAMP);
+ALTER TABLE weather_info ALTER COLUMN forecast_issued_at SET NOT NULL;
+ALTER TABLE weather_info
+    DROP CONSTRAINT weather_info_user_id_forecast_time_latitude_longitude_key,
+    ADD CONSTRAINT weather_info_forecast_version_key
+        UNIQUE (user_id, forecast_time, latitude, longitude, forecast_issued_at);
+-- 日预报和分钟级预报同样按发布时间保留每一次预报
+ALTER TABLE weather_daily ADD COLUMN forecast_issued_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE weather_minutely ADD COLUMN forecast_issued_at TIMESTAMP WITH TIME ZONE;
+UPDATE weather_daily
+    SET forecast_issued_at = COALESCE(updated_at, created_at, CURRENT_TIMESTAMP);
+UPDATE weather_minutely
+    SET forecast_issued_at = COALESCE(created_at, CURRENT_TIMESTAMP);
+ALTER TABLE weather_daily ALTER COLUMN forecast_issued_at SET NOT NULL;
+ALTER TABLE weather_minutely ALTER COLUMN forecast_issued_at SET NOT NULL;
+ALTER TABLE weather_daily
+    DROP CONSTRAINT weather_daily_user_id_forecast_time_latitude_longitude_key,
+    ADD CONSTRAINT weather_daily_forecast_version_key
+        UNIQUE (user_id, forecast_time, latitude, longitude, forecast_issued_at);
+ALTER TABLE weather_minutely
+    DROP CONSTRAINT weather_minutely_user_id_forecast_time_latitude_longitude_key,
+    ADD CONSTRAINT weather_minutely_forecast_version_key
+        UNIQUE (user_id, forecast_time, latitude, longitude, forecast_issued_at);
+-- 按时间点查询最新预报
+CREATE INDEX weather_info_location_forecast_time_idx
+    ON weather_info (latitude, longitude, forecast_time, forecast_issued_at DESC);
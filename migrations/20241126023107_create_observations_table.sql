@@ -0,0 +1,34 @@
+-- Add migration script here
+CREATE TABLE observations (
+    id uuid PRIMARY KEY,
+    -- 地理信息
+    latitude FLOAT NOT NULL,
+    longitude FLOAT NOT NULL,
+    city_name VARCHAR(100),
+    provider VARCHAR(32) NOT NULL,
+    -- 降水相关指标
+    rain_intensity FLOAT,
+    sleet_intensity FLOAT,
+    snow_intensity FLOAT,
+    -- 温湿度相关指标
+    temperature FLOAT,
+    temperature_apparent FLOAT,
+    dew_point FLOAT,
+    humidity FLOAT,
+    -- 风相关指标
+    wind_speed FLOAT,
+    wind_direction FLOAT,
+    wind_gust FLOAT,
+    -- 气压, 能见度, 云量
+    pressure_surface_level FLOAT,
+    visibility FLOAT,
+    cloud_cover FLOAT,
+    -- 天气代码
+    weather_code INTEGER,
+    -- 时间
+    observed_at TIMESTAMP NOT NULL,
+    -- 元数据,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (latitude, longitude, provider, observed_at)
+);
+CREATE INDEX observations_observed_at_idx ON observations (observed_at);
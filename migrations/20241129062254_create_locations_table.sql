@@ -0,0 +1,55 @@
+-- Add migration script here
+CREATE TABLE locations (
+    id uuid PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    -- 地理信息
+    latitude FLOAT NOT NULL,
+    longitude FLOAT NOT NULL,
+    timezone VARCHAR(64),
+    country VARCHAR(64),
+    -- 别名, 用于按名称查找
+    aliases TEXT[] NOT NULL DEFAULT '{}',
+    -- 地点由所有订阅者共享, 只有创建者可以修改名称, 别名和时区
+    created_by uuid REFERENCES users (user_id) ON DELETE SET NULL,
+    -- 元数据,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (latitude, longitude)
+);
+
+CREATE TABLE user_location_subscriptions (
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    location_id uuid NOT NULL REFERENCES locations (id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, location_id)
+);
+
+ALTER TABLE weather_info ADD COLUMN location_id uuid REFERENCES locations (id);
+CREATE INDEX weather_info_location_id_idx ON weather_info (location_id);
+
+-- 从已有预报中整理地点与订阅, 同一坐标取最近一次使用的城市名
+INSERT INTO locations (id, name, latitude, longitude)
+    SELECT gen_random_uuid(), COALESCE(city_name, ''), latitude, longitude
+    FROM (
+        SELECT DISTINCT ON (latitude, longitude) city_name, latitude, longitude
+        FROM weather_info
+        ORDER BY latitude, longitude, forecast_issued_at DESC
+    ) AS latest;
+UPDATE weather_info
+    SET location_id = locations.id
+    FROM locations
+    WHERE locations.latitude = weather_info.latitude
+        AND locations.longitude = weather_info.longitude;
+INSERT INTO user_location_subscriptions (user_id, location_id)
+    SELECT DISTINCT user_id, location_id
+    FROM weather_info
+    WHERE user_id IS NOT NULL;
+-- 已有地点以最早的订阅者为创建者
+UPDATE locations
+    SET created_by = first_subscription.user_id
+    FROM (
+        SELECT DISTINCT ON (location_id) location_id, user_id
+        FROM user_location_subscriptions
+        ORDER BY location_id, created_at, user_id
+    ) AS first_subscription
+    WHERE first_subscription.location_id = locations.id;
@@ -0,0 +1,24 @@
+-- Add migration script here
+-- 离线地名库, 由 GeoNames cities 数据导入
+CREATE TABLE gazetteer (
+    geoname_id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    ascii_name TEXT NOT NULL,
+    -- 地理信息
+    latitude FLOAT NOT NULL,
+    longitude FLOAT NOT NULL,
+    feature_code VARCHAR(10),
+    country_code VARCHAR(2),
+    admin1_code VARCHAR(20),
+    population BIGINT NOT NULL DEFAULT 0,
+    timezone VARCHAR(64),
+    -- 归一化后的名称, 别名, 中文名与拼音, 用于查找
+    search_names TEXT[] NOT NULL DEFAULT '{}'
+);
+CREATE INDEX gazetteer_search_names_idx ON gazetteer USING GIN (search_names);
+-- 一级行政区名称, 由 GeoNames admin1CodesASCII.txt 导入, code 形如 "CN.22"
+CREATE TABLE gazetteer_admin1 (
+    code VARCHAR(32) PRIMARY KEY,
+    name TEXT NOT NULL,
+    ascii_name TEXT NOT NULL
+);
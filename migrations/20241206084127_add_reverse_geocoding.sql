@@ -0,0 +1,16 @@
+-- Add migration script here
+-- 按坐标查找最近地名
+CREATE INDEX gazetteer_latitude_longitude_idx ON gazetteer (latitude, longitude);
+
+-- 地名库保存中文名, 按坐标命名的地点优先使用中文名
+ALTER TABLE gazetteer ADD COLUMN chinese_name TEXT;
+-- 已导入的数据取归一化名称中的第一个中文名, 重新导入后为 GeoNames 别名中的原名
+UPDATE gazetteer
+    SET chinese_name = (
+        SELECT s.name FROM UNNEST(search_names) WITH ORDINALITY AS s(name, position)
+        WHERE s.name ~ '^[一-鿿]+$'
+        ORDER BY s.position
+        LIMIT 1
+    );
+
+ALTER TABLE locations ADD COLUMN admin_region VARCHAR(100);
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- 位置统一以 WGS-84 存储, source_datum 记录用户提交坐标的原始坐标系 (wgs84 / gcj02 / bd09)
+ALTER TABLE locations ADD COLUMN source_datum VARCHAR(8) NOT NULL DEFAULT 'wgs84';
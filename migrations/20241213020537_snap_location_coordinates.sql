@@ -0,0 +1,126 @@
+-- Add migration script here
+-- 坐标统一保留 4 位小数 (约 11 米), 避免同一地点因浮点误差存成多条
+-- 取整后重合的地点合并成一个: 优先已在取整坐标上的, 其次最早创建的
+-- 预报和订阅改指向保留的地点, 其余地点删除
+CREATE TEMPORARY TABLE snapped_locations AS
+    SELECT
+        l.id,
+        FIRST_VALUE(l.id) OVER same_place AS survivor_id,
+        l.latitude AS old_latitude,
+        l.longitude AS old_longitude,
+        ROUND(l.latitude::NUMERIC, 4)::FLOAT AS latitude,
+        ROUND(l.longitude::NUMERIC, 4)::FLOAT AS longitude
+    FROM locations l
+    WINDOW same_place AS (
+        PARTITION BY ROUND(l.latitude::NUMERIC, 4), ROUND(l.longitude::NUMERIC, 4)
+        ORDER BY
+            (l.latitude = ROUND(l.latitude::NUMERIC, 4)::FLOAT
+                AND l.longitude = ROUND(l.longitude::NUMERIC, 4)::FLOAT) DESC,
+            l.created_at,
+            l.id
+    );
+-- 已在取整坐标上的地点无需处理
+DELETE FROM snapped_locations
+    WHERE old_latitude = latitude AND old_longitude = longitude;
+
+-- 订阅了被合并地点的用户改为订阅保留的地点
+INSERT INTO user_location_subscriptions (user_id, location_id, created_at)
+    SELECT s.user_id, m.survivor_id, MIN(s.created_at)
+    FROM user_location_subscriptions s
+    JOIN snapped_locations m ON m.id = s.location_id
+    WHERE m.id <> m.survivor_id
+    GROUP BY s.user_id, m.survivor_id
+    ON CONFLICT (user_id, location_id) DO NOTHING;
+UPDATE locations
+    SET created_by = merged.created_by
+    FROM (
+        SELECT DISTINCT ON (m.survivor_id) m.survivor_id, l.created_by
+        FROM snapped_locations m
+        JOIN locations l ON l.id = m.id
+        WHERE l.created_by IS NOT NULL
+        ORDER BY m.survivor_id, l.created_at, l.id
+    ) AS merged
+    WHERE locations.id = merged.survivor_id AND locations.created_by IS NULL;
+
+-- 移到取整坐标后会违反唯一约束的行只保留一条:
+-- 已在取整坐标上的行优先, 其次 id 最大的行
+DELETE FROM weather_info w
+    USING snapped_locations m
+    WHERE w.latitude = m.old_latitude AND w.longitude = m.old_longitude
+        AND EXISTS (
+            SELECT 1 FROM weather_info o
+            WHERE o.user_id = w.user_id
+                AND o.forecast_time = w.forecast_time
+                AND o.forecast_issued_at = w.forecast_issued_at
+                AND ROUND(o.latitude::NUMERIC, 4)::FLOAT = m.latitude
+                AND ROUND(o.longitude::NUMERIC, 4)::FLOAT = m.longitude
+                AND ((o.latitude = m.latitude AND o.longitude = m.longitude) OR o.id > w.id)
+        );
+DELETE FROM weather_daily w
+    USING snapped_locations m
+    WHERE w.latitude = m.old_latitude AND w.longitude = m.old_longitude
+        AND EXISTS (
+            SELECT 1 FROM weather_daily o
+            WHERE o.user_id = w.user_id
+                AND o.forecast_time = w.forecast_time
+                AND o.forecast_issued_at = w.forecast_issued_at
+                AND ROUND(o.latitude::NUMERIC, 4)::FLOAT = m.latitude
+                AND ROUND(o.longitude::NUMERIC, 4)::FLOAT = m.longitude
+                AND ((o.latitude = m.latitude AND o.longitude = m.longitude) OR o.id > w.id)
+        );
+DELETE FROM weather_minutely w
+    USING snapped_locations m
+    WHERE w.latitude = m.old_latitude AND w.longitude = m.old_longitude
+        AND EXISTS (
+            SELECT 1 FROM weather_minutely o
+            WHERE o.user_id = w.user_id
+                AND o.forecast_time = w.forecast_time
+                AND o.forecast_issued_at = w.forecast_issued_at
+                AND ROUND(o.latitude::NUMERIC, 4)::FLOAT = m.latitude
+                AND ROUND(o.longitude::NUMERIC, 4)::FLOAT = m.longitude
+                AND ((o.latitude = m.latitude AND o.longitude = m.longitude) OR o.id > w.id)
+        );
+DELETE FROM observations w
+    USING snapped_locations m
+    WHERE w.latitude = m.old_latitude AND w.longitude = m.old_longitude
+        AND EXISTS (
+            SELECT 1 FROM observations o
+            WHERE o.provider = w.provider
+                AND o.observed_at = w.observed_at
+                AND ROUND(o.latitude::NUMERIC, 4)::FLOAT = m.latitude
+                AND ROUND(o.longitude::NUMERIC, 4)::FLOAT = m.longitude
+                AND ((o.latitude = m.latitude AND o.longitude = m.longitude) OR o.id > w.id)
+        );
+
+UPDATE weather_info
+    SET latitude = m.latitude, longitude = m.longitude, location_id = m.survivor_id
+    FROM snapped_locations m
+    WHERE weather_info.location_id = m.id
+        OR (weather_info.latitude = m.old_latitude AND weather_info.longitude = m.old_longitude);
+UPDATE weather_daily
+    SET latitude = m.latitude, longitude = m.longitude
+    FROM snapped_locations m
+    WHERE weather_daily.latitude = m.old_latitude AND weather_daily.longitude = m.old_longitude;
+UPDATE weather_minutely
+    SET latitude = m.latitude, longitude = m.longitude
+    FROM snapped_locations m
+    WHERE weather_minutely.latitude = m.old_latitude AND weather_minutely.longitude = m.old_longitude;
+UPDATE forecast_raw
+    SET latitude = m.latitude, longitude = m.longitude
+    FROM snapped_locations m
+    WHERE forecast_raw.latitude = m.old_latitude AND forecast_raw.longitude = m.old_longitude;
+UPDATE observations
+    SET latitude = m.latitude, longitude = m.longitude
+    FROM snapped_locations m
+    WHERE observations.latitude = m.old_latitude AND observations.longitude = m.old_longitude;
+
+-- 订阅随地点级联删除
+DELETE FROM locations
+    USING snapped_locations m
+    WHERE locations.id = m.id AND m.id <> m.survivor_id;
+UPDATE locations
+    SET latitude = m.latitude, longitude = m.longitude
+    FROM snapped_locations m
+    WHERE locations.id = m.survivor_id AND m.id = m.survivor_id;
+
+DROP TABLE snapped_locations;
@@ -0,0 +1,29 @@
+-- Add migration script here
+-- metric 为入库数值的标准单位制 (而非 SI), 各服务商的数值在入库前统一换算为 metric, units 记录每行数值所用的单位制
+ALTER TABLE weather_info ADD COLUMN units VARCHAR(8) NOT NULL DEFAULT 'metric';
+ALTER TABLE weather_daily ADD COLUMN units VARCHAR(8) NOT NULL DEFAULT 'metric';
+ALTER TABLE weather_minutely ADD COLUMN units VARCHAR(8) NOT NULL DEFAULT 'metric';
+ALTER TABLE observations ADD COLUMN units VARCHAR(8) NOT NULL DEFAULT 'metric';
+-- metric 单位制下各指标的单位
+COMMENT ON COLUMN weather_info.temperature IS '°C';
+COMMENT ON COLUMN weather_info.temperature_apparent IS '°C';
+COMMENT ON COLUMN weather_info.dew_point IS '°C';
+COMMENT ON COLUMN weather_info.rain_intensity IS 'mm/hr';
+COMMENT ON COLUMN weather_info.freezing_rain_intensity IS 'mm/hr';
+COMMENT ON COLUMN weather_info.sleet_intensity IS 'mm/hr';
+COMMENT ON COLUMN weather_info.snow_intensity IS 'mm/hr';
+COMMENT ON COLUMN weather_info.wind_speed IS 'm/s';
+COMMENT ON COLUMN weather_info.wind_gust IS 'm/s';
+COMMENT ON COLUMN weather_info.pressure_surface_level IS 'hPa';
+COMMENT ON COLUMN weather_info.visibility IS 'km';
+COMMENT ON COLUMN weather_info.cloud_base IS 'km';
+COMMENT ON COLUMN weather_info.cloud_ceiling IS 'km';
+COMMENT ON COLUMN weather_daily.temperature_min IS '°C';
+COMMENT ON COLUMN weather_daily.temperature_max IS '°C';
+COMMENT ON COLUMN weather_daily.precipitation_accumulation IS 'mm';
+COMMENT ON COLUMN weather_minutely.precipitation_intensity IS 'mm/hr';
+COMMENT ON COLUMN observations.temperature IS '°C';
+COMMENT ON COLUMN observations.wind_speed IS 'm/s';
+COMMENT ON COLUMN observations.wind_gust IS 'm/s';
+COMMENT ON COLUMN observations.pressure_surface_level IS 'hPa';
+COMMENT ON COLUMN observations.visibility IS 'km';
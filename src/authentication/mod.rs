@@ -1,3 +1,5 @@
 mod password;
+mod token;
 
-pub use password::{Credentials, validate_credentials,AuthError};
\ No newline at end of file
+pub use password::{Credentials, validate_credentials,AuthError};
+pub use token::{ApiAuthError, ApiUser};
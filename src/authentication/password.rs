@@ -1,7 +1,6 @@
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use secrecy::{ExposeSecret, SecretBox};
 use sqlx::PgPool;
-use tracing::error;
 use uuid::Uuid;
 
 use crate::{errors::DbError, telemetry::spawn_blocking_with_tracing};
@@ -81,15 +80,12 @@ fn verify_password_hash(
 ) -> Result<(), AuthError> {
     let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())
         .map_err(|e| {
-            AuthError::UnexpectedError(format!(
-                "Error during password hashing, details: {}",
-                e.to_string()
-            ))
+            AuthError::UnexpectedError(format!("Error during password hashing, details: {}", e))
         })?;
     Argon2::default()
         .verify_password(
             password_candidate.expose_secret().as_bytes(),
             &expected_password_hash,
         )
-        .map_err(|_| AuthError::InvalidCredentials(format!("Incorrect username or password.",)))
+        .map_err(|_| AuthError::InvalidCredentials("Incorrect username or password.".to_string()))
 }
@@ -0,0 +1,82 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{errors::DbError, start_up::AppState};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ApiAuthError {
+    #[error("Missing bearer token")]
+    MissingToken,
+    #[error("Invalid token")]
+    InvalidToken,
+    #[error(transparent)]
+    DatabaseError(#[from] DbError),
+}
+
+impl IntoResponse for ApiAuthError {
+    fn into_response(self) -> Response {
+        let (status_code, status) = match &self {
+            ApiAuthError::MissingToken | ApiAuthError::InvalidToken => {
+                (StatusCode::UNAUTHORIZED, "UNAUTHORIZED")
+            }
+            ApiAuthError::DatabaseError(e) => {
+                error!("Failed to validate api token, details: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "DATABASE_ERROR")
+            }
+        };
+        let body = Json(json!({"status": status, "content": self.to_string()}));
+        (status_code, body).into_response()
+    }
+}
+
+/// The user owning the `Authorization: Bearer <token>` of an API request,
+/// using the same per-user token as `/update_weather`.
+pub struct ApiUser {
+    pub user_id: Uuid,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ApiUser {
+    type Rejection = ApiAuthError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(ApiAuthError::MissingToken)?;
+        ApiUser::from_token(token.trim(), &state.connect_pool).await
+    }
+}
+
+impl ApiUser {
+    /// Authenticates a token given elsewhere than in the `Authorization`
+    /// header, such as in a calendar subscription URL.
+    pub async fn from_token(token: &str, pool: &PgPool) -> Result<Self, ApiAuthError> {
+        let user_id = get_user_id_by_api_token(token, pool)
+            .await?
+            .ok_or(ApiAuthError::InvalidToken)?;
+        Ok(ApiUser { user_id })
+    }
+}
+
+#[tracing::instrument(name = "Get user id by api token", skip(token, pool))]
+async fn get_user_id_by_api_token(token: &str, pool: &PgPool) -> Result<Option<Uuid>, DbError> {
+    let user_id = sqlx::query_scalar!("SELECT user_id FROM tokens WHERE token = $1", token)
+        .fetch_optional(pool)
+        .await?;
+    Ok(user_id)
+}
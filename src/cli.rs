@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use uuid::Uuid;
+
+#[derive(Parser)]
+#[command(version, about = "Weather forecast backend")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the HTTP server (the default).
+    Serve,
+    /// Re-parse archived raw forecasts and upsert them into weather_info.
+    Reprocess {
+        /// Earliest request time, RFC 3339 or `YYYY-MM-DDTHH:MM` in UTC.
+        #[arg(long, default_value = "")]
+        from: String,
+        /// Request time to stop before, same formats as `--from`.
+        #[arg(long, default_value = "")]
+        to: String,
+        /// Only responses archived for this `lat,lon`.
+        #[arg(long, default_value = "")]
+        location: String,
+        /// Only responses archived for this user; every user's by default.
+        #[arg(long)]
+        user_id: Option<Uuid>,
+    },
+    /// Record current conditions for every recently forecast location.
+    Observe,
+    /// Print bias, MAE and RMSE of stored forecasts against observations.
+    Verify {
+        /// Earliest forecast time, RFC 3339 or `YYYY-MM-DDTHH:MM` in UTC.
+        #[arg(long, default_value = "")]
+        from: String,
+        /// Forecast time to stop before, same formats as `--from`.
+        #[arg(long, default_value = "")]
+        to: String,
+        /// Only forecasts stored for this `lat,lon`.
+        #[arg(long, default_value = "")]
+        location: String,
+    },
+    /// Write stored forecasts as CSV or NDJSON, streaming them from the database.
+    Export {
+        /// `csv` or `ndjson`.
+        #[arg(long, default_value = "csv")]
+        format: String,
+        /// Earliest forecast time, RFC 3339 or `YYYY-MM-DDTHH:MM` in UTC.
+        #[arg(long, default_value = "")]
+        from: String,
+        /// Forecast time to stop before, same formats as `--from`.
+        #[arg(long, default_value = "")]
+        to: String,
+        /// Only forecasts stored for this `lat,lon`.
+        #[arg(long, default_value = "")]
+        location: String,
+        /// The user whose forecasts are exported; rows carry no user column.
+        #[arg(long)]
+        user_id: Uuid,
+        /// Comma separated columns, all of them by default.
+        #[arg(long)]
+        columns: Option<String>,
+        /// `metric`, `imperial` or `si`.
+        #[arg(long, default_value = "metric")]
+        units: String,
+        /// `ms`, `kmh`, `mph` or `beaufort`; the one of `--units` by default.
+        #[arg(long)]
+        wind_speed_unit: Option<String>,
+        /// File to write instead of standard output.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Load a GeoNames cities dump such as `cities15000.txt` into the gazetteer.
+    LoadGazetteer {
+        /// Path of the tab separated GeoNames file.
+        path: PathBuf,
+        /// GeoNames `admin1CodesASCII.txt`, naming provinces and states.
+        #[arg(long)]
+        admin1: Option<PathBuf>,
+    },
+}
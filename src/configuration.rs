@@ -1,9 +1,14 @@
+use std::sync::Arc;
+
 use config::Config;
 use secrecy::{ExposeSecret, SecretString};
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 use tracing::error;
 
-use crate::weather_client::WeatherClient;
+use crate::weather_client::{
+    OpenMeteoProvider, QWeatherAuth, QWeatherJwtSigner, QWeatherProvider, TomorrowIoProvider,
+    WeatherClient, WeatherProvider, WeatherProviderKind, QWEATHER_FORECAST_HOURS,
+};
 
 #[derive(serde::Deserialize, Clone)]
 pub enum Environment {
@@ -40,6 +45,8 @@ pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
     pub weather_client: WeatherClientSettings,
+    pub scheduler: SchedulerSettings,
+    pub forecasts: ForecastSettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -61,9 +68,65 @@ pub struct ApplicationSettings {
 
 #[derive(serde::Deserialize, Clone)]
 pub struct WeatherClientSettings {
-    pub base_url: String,
-    pub api_key: SecretString,
     pub timeout_milliseconds: u64,
+    /// Tried in order; see `WeatherClient` for when the next one is used.
+    pub providers: Vec<WeatherProviderSettings>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SchedulerSettings {
+    pub enabled: bool,
+    /// Time between the starts of two refresh cycles.
+    pub interval_seconds: u64,
+    /// Each location waits a random delay up to this long before refreshing.
+    pub jitter_seconds: u64,
+    /// Locations refreshed at the same time.
+    pub max_concurrency: usize,
+    /// Also record current conditions after each cycle, for verification.
+    pub record_observations: bool,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct ForecastSettings {
+    /// Search radius of `/api/v1/forecasts/nearest` when none is given.
+    pub default_radius_km: f64,
+    /// Largest search radius a client may ask for.
+    pub max_radius_km: f64,
+    /// `/update_weather` and the scheduler reuse a forecast archived this
+    /// recently for the same location instead of calling the provider again;
+    /// 0 disables it.
+    pub cache_ttl_seconds: u64,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct WeatherProviderSettings {
+    pub provider: WeatherProviderKind,
+    pub base_url: String,
+    pub api_key: Option<SecretString>,
+    #[serde(default)]
+    pub qweather: QWeatherSettings,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct QWeatherSettings {
+    pub forecast_hours: u16,
+    pub jwt: Option<QWeatherJwtSettings>,
+}
+
+impl Default for QWeatherSettings {
+    fn default() -> Self {
+        QWeatherSettings {
+            forecast_hours: 24,
+            jwt: None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct QWeatherJwtSettings {
+    pub key_id: String,
+    pub project_id: String,
+    pub private_key: SecretString,
 }
 
 pub fn get_configuration() -> Result<Settings, config::ConfigError> {
@@ -81,9 +144,8 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
         )
         .add_source(config::Environment::with_prefix("app").separator("_"))
         .build()
-        .map_err(|e| {
-            error!("config read error, details: {}", e.to_string());
-            e
+        .inspect_err(|e| {
+            error!("config read error, details: {}", e);
         })?;
 
     settings.try_deserialize()
@@ -113,8 +175,63 @@ impl WeatherClientSettings {
     pub fn timeout(&self) -> std::time::Duration {
         std::time::Duration::from_millis(self.timeout_milliseconds)
     }
-    pub fn client(self) -> WeatherClient {
+    pub fn client(self) -> Result<WeatherClient, anyhow::Error> {
         let timeout = self.timeout();
-        WeatherClient::new(self.base_url, self.api_key, timeout)
+        if self.providers.is_empty() {
+            anyhow::bail!("weather_client.providers must list at least one provider");
+        }
+        let providers = self
+            .providers
+            .into_iter()
+            .map(|provider| provider.provider(timeout))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(WeatherClient::new(providers))
+    }
+}
+
+impl WeatherProviderSettings {
+    pub fn provider(
+        self,
+        timeout: std::time::Duration,
+    ) -> Result<Arc<dyn WeatherProvider>, anyhow::Error> {
+        let provider: Arc<dyn WeatherProvider> = match self.provider {
+            WeatherProviderKind::TomorrowIo => {
+                let api_key = self
+                    .api_key
+                    .ok_or_else(|| anyhow::anyhow!("tomorrow_io provider requires an api_key"))?;
+                Arc::new(TomorrowIoProvider::new(self.base_url, api_key, timeout))
+            }
+            WeatherProviderKind::OpenMeteo => {
+                Arc::new(OpenMeteoProvider::new(self.base_url, timeout))
+            }
+            WeatherProviderKind::QWeather => {
+                let forecast_hours = self.qweather.forecast_hours;
+                if !QWEATHER_FORECAST_HOURS.contains(&forecast_hours) {
+                    anyhow::bail!(
+                        "qweather forecast_hours must be one of {:?}, got {}",
+                        QWEATHER_FORECAST_HOURS,
+                        forecast_hours
+                    );
+                }
+                let auth = match (self.qweather.jwt, self.api_key) {
+                    (Some(jwt), _) => QWeatherAuth::Jwt(Box::new(QWeatherJwtSigner::new(
+                        jwt.key_id,
+                        jwt.project_id,
+                        &jwt.private_key,
+                    )?)),
+                    (None, Some(api_key)) => QWeatherAuth::Key(api_key),
+                    (None, None) => {
+                        anyhow::bail!("qweather provider requires an api_key or jwt settings")
+                    }
+                };
+                Arc::new(QWeatherProvider::new(
+                    self.base_url,
+                    auth,
+                    forecast_hours,
+                    timeout,
+                ))
+            }
+        };
+        Ok(provider)
     }
 }
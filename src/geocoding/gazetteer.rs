@@ -0,0 +1,202 @@
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use sqlx::PgPool;
+use thiserror::Error;
+use tracing::info;
+
+use crate::errors::DbError;
+
+use super::normalize_name;
+
+#[derive(Error, Debug)]
+pub enum GazetteerError {
+    #[error("Failed to read gazetteer file: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Invalid gazetteer line {line}: {message}")]
+    FormatError { line: usize, message: String },
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] DbError),
+}
+
+/// One row of a GeoNames `cities*.txt` dump, which is tab separated with
+/// the alternate names, including Chinese names and pinyin, comma separated.
+struct GeoNamesRow {
+    geoname_id: i32,
+    name: String,
+    ascii_name: String,
+    latitude: f64,
+    longitude: f64,
+    feature_code: String,
+    country_code: String,
+    admin1_code: String,
+    population: i64,
+    timezone: String,
+    chinese_name: Option<String>,
+    search_names: Vec<String>,
+}
+
+impl GeoNamesRow {
+    fn parse(line: &str, line_number: usize) -> Result<Self, GazetteerError> {
+        let format_error = |message: String| GazetteerError::FormatError {
+            line: line_number,
+            message,
+        };
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 19 {
+            return Err(format_error(format!(
+                "expected 19 columns, found {}",
+                fields.len()
+            )));
+        }
+        let number = |index: usize| {
+            fields[index]
+                .parse::<f64>()
+                .map_err(|e| format_error(format!("column {}: {}", index + 1, e)))
+        };
+        let search_names: BTreeSet<String> = [fields[1], fields[2]]
+            .into_iter()
+            .chain(fields[3].split(','))
+            .map(normalize_name)
+            .filter(|name| !name.is_empty())
+            .collect();
+        Ok(GeoNamesRow {
+            geoname_id: fields[0]
+                .parse()
+                .map_err(|e| format_error(format!("geonameid: {}", e)))?,
+            name: fields[1].to_string(),
+            ascii_name: fields[2].to_string(),
+            latitude: number(4)?,
+            longitude: number(5)?,
+            feature_code: fields[7].to_string(),
+            country_code: fields[8].to_string(),
+            admin1_code: fields[10].to_string(),
+            population: fields[14].parse().unwrap_or(0),
+            timezone: fields[17].to_string(),
+            chinese_name: fields[3]
+                .split(',')
+                .map(str::trim)
+                .find(|name| is_chinese(name))
+                .map(str::to_string),
+            search_names: search_names.into_iter().collect(),
+        })
+    }
+}
+
+/// Whether `name` is written in Chinese characters only, like the Chinese
+/// entries among GeoNames alternate names.
+fn is_chinese(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| ('\u{4e00}'..='\u{9fff}').contains(&c))
+}
+
+/// Loads a GeoNames cities dump (e.g. `cities15000.txt` from
+/// <https://download.geonames.org/export/dump/>) into `gazetteer`, replacing
+/// rows with the same geoname id. Returns the number of rows loaded.
+#[tracing::instrument(name = "Load gazetteer", skip(pool))]
+pub async fn load_gazetteer(path: &Path, pool: &PgPool) -> Result<usize, GazetteerError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| GazetteerError::DatabaseError(e.into()))?;
+    let mut loaded = 0;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let row = GeoNamesRow::parse(&line, index + 1)?;
+        sqlx::query!(
+            r#"
+            INSERT INTO gazetteer
+                (geoname_id, name, ascii_name, latitude, longitude, feature_code, country_code, admin1_code, population, timezone, search_names, chinese_name)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                ON CONFLICT (geoname_id) DO UPDATE
+                SET
+                    name = $2,
+                    ascii_name = $3,
+                    latitude = $4,
+                    longitude = $5,
+                    feature_code = $6,
+                    country_code = $7,
+                    admin1_code = $8,
+                    population = $9,
+                    timezone = $10,
+                    search_names = $11,
+                    chinese_name = $12
+            "#,
+            row.geoname_id,
+            row.name,
+            row.ascii_name,
+            row.latitude,
+            row.longitude,
+            row.feature_code,
+            row.country_code,
+            row.admin1_code,
+            row.population,
+            row.timezone,
+            &row.search_names,
+            row.chinese_name,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| GazetteerError::DatabaseError(e.into()))?;
+        loaded += 1;
+    }
+    transaction
+        .commit()
+        .await
+        .map_err(|e| GazetteerError::DatabaseError(e.into()))?;
+    info!(loaded, "Loaded gazetteer");
+    Ok(loaded)
+}
+
+/// Loads GeoNames `admin1CodesASCII.txt`, which names the first-level
+/// administrative divisions referenced by `gazetteer.admin1_code`.
+#[tracing::instrument(name = "Load admin1 codes", skip(pool))]
+pub async fn load_admin1_codes(path: &Path, pool: &PgPool) -> Result<usize, GazetteerError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| GazetteerError::DatabaseError(e.into()))?;
+    let mut loaded = 0;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 4 {
+            return Err(GazetteerError::FormatError {
+                line: index + 1,
+                message: format!("expected 4 columns, found {}", fields.len()),
+            });
+        }
+        sqlx::query!(
+            r#"
+            INSERT INTO gazetteer_admin1 (code, name, ascii_name)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (code) DO UPDATE
+                SET name = $2, ascii_name = $3
+            "#,
+            fields[0],
+            fields[1],
+            fields[2],
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| GazetteerError::DatabaseError(e.into()))?;
+        loaded += 1;
+    }
+    transaction
+        .commit()
+        .await
+        .map_err(|e| GazetteerError::DatabaseError(e.into()))?;
+    info!(loaded, "Loaded admin1 codes");
+    Ok(loaded)
+}
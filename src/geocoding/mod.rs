@@ -0,0 +1,40 @@
+mod gazetteer;
+mod search;
+mod timezone;
+
+pub use gazetteer::{load_admin1_codes, load_gazetteer, GazetteerError};
+pub use search::{geocode, reverse_geocode, search_places, GeocodeError, Place};
+pub use timezone::{local_day_bounds, resolve_timezone, timezone_at};
+
+/// Administrative suffixes users may or may not type, e.g. "苏州市" for
+/// "苏州" or "Haidian Qu" for "Haidian".
+const CHINESE_SUFFIXES: [&str; 7] = ["特别行政区", "自治州", "自治县", "市", "区", "县", "省"];
+const PINYIN_SUFFIXES: [&str; 6] = ["shi", "qu", "xian", "city", "district", "county"];
+
+/// Folds a place name to the form stored in `gazetteer.search_names`:
+/// lowercase, without spaces or punctuation and without an administrative
+/// suffix, so names and queries compare equal regardless of spelling details.
+pub fn normalize_name(name: &str) -> String {
+    let lowercase = name.trim().to_lowercase();
+    let mut words: Vec<&str> = lowercase
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.len() > 1 && PINYIN_SUFFIXES.contains(words.last().unwrap()) {
+        words.pop();
+    }
+    let mut normalized: String = words
+        .concat()
+        .chars()
+        .filter(|c| !matches!(c, '\'' | '’' | '.' | '·' | '(' | ')'))
+        .collect();
+    for suffix in CHINESE_SUFFIXES {
+        if let Some(stem) = normalized.strip_suffix(suffix) {
+            if stem.chars().count() >= 2 {
+                normalized = stem.to_string();
+                break;
+            }
+        }
+    }
+    normalized
+}
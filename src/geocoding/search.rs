@@ -0,0 +1,210 @@
+use serde::Serialize;
+use sqlx::PgPool;
+use thiserror::Error;
+
+use crate::{
+    errors::DbError,
+    routers::longitude_ranges,
+    weather_client::{Coordinate, Datum},
+};
+
+use super::normalize_name;
+
+/// Candidates returned for an ambiguous name, most populous first.
+const MAX_CANDIDATES: i64 = 10;
+/// Reverse geocoding only names a coordinate after a place this close.
+const MAX_REVERSE_DISTANCE_KM: f64 = 30.0;
+const KM_PER_DEGREE: f64 = 111.32;
+
+/// A populated place from the gazetteer.
+#[derive(Serialize, Debug, Clone)]
+pub struct Place {
+    pub geoname_id: i32,
+    pub name: String,
+    /// Chinese name among the GeoNames alternate names, when there is one.
+    pub chinese_name: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub country_code: Option<String>,
+    pub admin1_code: Option<String>,
+    /// Name of the first-level administrative division, when loaded.
+    pub admin1_name: Option<String>,
+    pub population: i64,
+    pub timezone: Option<String>,
+}
+
+impl Place {
+    /// The Chinese name when known, otherwise the GeoNames name.
+    pub fn display_name(&self) -> &str {
+        self.chinese_name.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn coordinate(&self) -> Coordinate {
+        Coordinate {
+            latitude: self.latitude,
+            longitude: self.longitude,
+            datum: Datum::Wgs84,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum GeocodeError {
+    #[error("No place named {0:?} was found")]
+    NotFound(String),
+    #[error("{query:?} matches several places: {}; {}", describe(.candidates), hint(.query, .candidates))]
+    Ambiguous {
+        query: String,
+        candidates: Vec<Place>,
+    },
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] DbError),
+}
+
+fn describe(candidates: &[Place]) -> String {
+    candidates
+        .iter()
+        .map(|place| {
+            let region = match &place.admin1_name {
+                Some(admin1_name) => format!("{}, ", admin1_name),
+                None => String::new(),
+            };
+            format!(
+                "{} ({}{}, {:.4},{:.4})",
+                place.name,
+                region,
+                place.country_code.as_deref().unwrap_or("?"),
+                place.latitude,
+                place.longitude
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Suggests qualifying the query with a candidate's province or state, or
+/// with a country code when no candidate has one loaded.
+fn hint(query: &str, candidates: &[Place]) -> String {
+    match candidates
+        .iter()
+        .find_map(|place| place.admin1_name.as_deref())
+    {
+        Some(admin1_name) => format!(
+            "add the province or state like \"{}, {}\" or send coordinates",
+            query, admin1_name
+        ),
+        None => format!(
+            "add a country code like \"{}, CN\" or send coordinates",
+            query
+        ),
+    }
+}
+
+/// Splits optional trailing qualifiers off a query: an ISO country code, so
+/// "Suzhou, CN" searches for "Suzhou" in China only, and before it the name
+/// of a first-level division, so "Suzhou, Anhui" or "Suzhou, Anhui, CN"
+/// picks the Suzhou in Anhui. The division is returned normalized.
+fn split_qualifiers(query: &str) -> (&str, Option<String>, Option<String>) {
+    let (mut name, mut country) = (query, None);
+    if let Some((rest, code)) = query.rsplit_once([',', '，']) {
+        let code = code.trim();
+        if code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()) {
+            (name, country) = (rest, Some(code.to_ascii_uppercase()));
+        }
+    }
+    match name.rsplit_once([',', '，']) {
+        Some((name, admin1)) => (name, country, Some(normalize_name(admin1))),
+        None => (name, country, None),
+    }
+}
+
+/// Returns up to ten places matching `query` by name, alternate name,
+/// Chinese name or pinyin, most populous first. A first-level division
+/// matches its GeoNames name, ignoring case, spaces and punctuation.
+#[tracing::instrument(name = "Search gazetteer", skip(pool))]
+pub async fn search_places(query: &str, pool: &PgPool) -> Result<Vec<Place>, DbError> {
+    let (name, country_code, admin1) = split_qualifiers(query);
+    let places = sqlx::query_as!(
+        Place,
+        r#"
+        SELECT g.geoname_id, g.name, g.chinese_name, g.latitude, g.longitude, g.country_code, g.admin1_code,
+            a.name AS "admin1_name?", g.population, g.timezone
+        FROM gazetteer g
+        LEFT JOIN gazetteer_admin1 a ON a.code = g.country_code || '.' || g.admin1_code
+        WHERE g.search_names @> ARRAY[$1]
+            AND ($2::TEXT IS NULL OR g.country_code = $2)
+            AND ($4::TEXT IS NULL OR $4 IN (
+                REGEXP_REPLACE(LOWER(a.name), '[\s\-''.]', '', 'g'),
+                REGEXP_REPLACE(LOWER(a.ascii_name), '[\s\-''.]', '', 'g')
+            ))
+        ORDER BY g.population DESC, g.geoname_id
+        LIMIT $3
+        "#,
+        normalize_name(name),
+        country_code,
+        MAX_CANDIDATES,
+        admin1,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(places)
+}
+
+/// Resolves `query` to exactly one place, failing with every candidate when
+/// the name is ambiguous.
+pub async fn geocode(query: &str, pool: &PgPool) -> Result<Place, GeocodeError> {
+    let mut places = search_places(query, pool).await?;
+    match places.len() {
+        0 => Err(GeocodeError::NotFound(query.to_string())),
+        1 => Ok(places.remove(0)),
+        _ => Err(GeocodeError::Ambiguous {
+            query: query.to_string(),
+            candidates: places,
+        }),
+    }
+}
+
+/// Returns the gazetteer place nearest to `coordinate`, if one lies within
+/// 30 km. The gazetteer is WGS-84, so other datums are converted first.
+/// Candidates are narrowed by a bounding box, wrapped at the antimeridian,
+/// and ordered by great-circle distance.
+#[tracing::instrument(name = "Reverse geocode", skip(coordinate, pool))]
+pub async fn reverse_geocode(
+    coordinate: &Coordinate,
+    pool: &PgPool,
+) -> Result<Option<Place>, DbError> {
+    let coordinate = coordinate.to_wgs84();
+    let latitude_delta = MAX_REVERSE_DISTANCE_KM / KM_PER_DEGREE;
+    let longitude_delta = latitude_delta / coordinate.latitude.to_radians().cos().max(0.01);
+    let [(west, east), (wrapped_west, wrapped_east)] =
+        longitude_ranges(coordinate.longitude, longitude_delta);
+    let place = sqlx::query_as!(
+        Place,
+        r#"
+        SELECT g.geoname_id, g.name, g.chinese_name, g.latitude, g.longitude, g.country_code, g.admin1_code,
+            a.name AS "admin1_name?", g.population, g.timezone
+        FROM gazetteer g
+        LEFT JOIN gazetteer_admin1 a ON a.code = g.country_code || '.' || g.admin1_code
+        WHERE g.latitude BETWEEN $1::FLOAT - $3::FLOAT AND $1 + $3
+            AND (g.longitude BETWEEN $4 AND $5 OR g.longitude BETWEEN $6 AND $7)
+            AND 2 * 6371 * ASIN(SQRT(
+                POWER(SIN(RADIANS(g.latitude - $1) / 2), 2)
+                + COS(RADIANS($1)) * COS(RADIANS(g.latitude)) * POWER(SIN(RADIANS(g.longitude - $2) / 2), 2)
+            )) <= $8::FLOAT
+        ORDER BY POWER(SIN(RADIANS(g.latitude - $1) / 2), 2)
+            + COS(RADIANS($1)) * COS(RADIANS(g.latitude)) * POWER(SIN(RADIANS(g.longitude - $2) / 2), 2)
+        LIMIT 1
+        "#,
+        coordinate.latitude,
+        coordinate.longitude,
+        latitude_delta,
+        west,
+        east,
+        wrapped_west,
+        wrapped_east,
+        MAX_REVERSE_DISTANCE_KM,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(place)
+}
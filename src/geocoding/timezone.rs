@@ -0,0 +1,47 @@
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Days, NaiveDate, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use tzf_rs::DefaultFinder;
+
+use crate::weather_client::Coordinate;
+
+/// Timezone boundaries bundled into the binary, expanded on first use.
+static FINDER: LazyLock<DefaultFinder> = LazyLock::new(DefaultFinder::new);
+
+/// Looks up the IANA timezone containing `coordinate` offline. Open sea
+/// resolves to one of the `Etc/GMT` zones.
+pub fn timezone_at(coordinate: &Coordinate) -> Option<Tz> {
+    let coordinate = coordinate.to_wgs84();
+    FINDER
+        .get_tz_name(coordinate.longitude, coordinate.latitude)
+        .parse()
+        .ok()
+}
+
+/// The stored timezone when it is valid, else the one at `coordinate`, else
+/// UTC.
+pub fn resolve_timezone(timezone: Option<&str>, coordinate: &Coordinate) -> Tz {
+    timezone
+        .and_then(|timezone| timezone.parse().ok())
+        .or_else(|| timezone_at(coordinate))
+        .unwrap_or(Tz::UTC)
+}
+
+/// The instants `day` starts and ends at in `timezone`. Days around a DST
+/// change last 23 or 25 hours, and a day whose midnight is skipped starts at
+/// its first existing hour.
+pub fn local_day_bounds(day: NaiveDate, timezone: Tz) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start_of = |day: NaiveDate| {
+        let midnight = day.and_time(Default::default());
+        (0..24)
+            .find_map(|hour| {
+                timezone
+                    .from_local_datetime(&(midnight + TimeDelta::hours(hour)))
+                    .earliest()
+            })
+            .map(|start| start.with_timezone(&Utc))
+            .unwrap_or_else(|| midnight.and_utc())
+    };
+    (start_of(day), start_of(day + Days::new(1)))
+}
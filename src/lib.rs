@@ -1,7 +1,10 @@
+pub mod cli;
 pub mod telemetry;
 pub mod configuration;
 pub mod start_up;
 pub mod routers;
+pub mod scheduler;
 pub mod weather_client;
 pub mod authentication;
-pub mod errors;
\ No newline at end of file
+pub mod errors;
+pub mod geocoding;
\ No newline at end of file
@@ -1,8 +1,21 @@
-use std::fmt::{Debug, Display};
+use clap::Parser;
+use futures::StreamExt;
+use std::{
+    fmt::{Debug, Display},
+    fs::File,
+    io::{self, BufWriter, Write},
+};
 use tokio::task::JoinError;
 use weather_forecast_wechat_bot::{
+    cli::{Cli, Command},
     configuration::get_configuration,
-    start_up::Application,
+    geocoding::{load_admin1_codes, load_gazetteer},
+    routers::{
+        record_observations, reprocess_raw_forecasts, stream_forecasts, verify_forecasts,
+        ExportFormat, ForecastExport, ForecastFilter, UnitSystem, Units, WindSpeedUnit,
+    },
+    scheduler::Scheduler,
+    start_up::{get_connection_pool, Application},
     telemetry::{get_subscriber, init_subscriber},
 };
 
@@ -11,12 +24,102 @@ async fn main() -> anyhow::Result<()> {
     let subscriber = get_subscriber();
     init_subscriber(subscriber);
 
+    let cli = Cli::parse();
     let configuration = get_configuration().expect("Failed to read configuration.");
-    let application = Application::build(configuration.clone()).await?;
-    let application_task = tokio::spawn(application.run_until_stopped());
-    tokio::select! {
-        o = application_task => report_exit("API", o),
-    };
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            let application = Application::build(configuration.clone()).await?;
+            let application_task = tokio::spawn(application.run_until_stopped());
+            let scheduler = Scheduler::build(configuration.clone())?;
+            let scheduler_task = tokio::spawn(scheduler.run_until_stopped());
+            tokio::select! {
+                o = application_task => report_exit("API", o),
+                o = scheduler_task => report_exit("Scheduler", o),
+            };
+        }
+        Command::Reprocess {
+            from,
+            to,
+            location,
+            user_id,
+        } => {
+            let filter =
+                ForecastFilter::parse(&from, &to, &location).map_err(anyhow::Error::msg)?;
+            let pool = get_connection_pool(configuration.database);
+            reprocess_raw_forecasts(user_id, &filter, &pool).await?;
+        }
+        Command::Observe => {
+            let client = configuration.weather_client.client()?;
+            let pool = get_connection_pool(configuration.database);
+            record_observations(&client, &pool).await?;
+        }
+        Command::Verify { from, to, location } => {
+            let filter =
+                ForecastFilter::parse(&from, &to, &location).map_err(anyhow::Error::msg)?;
+            let pool = get_connection_pool(configuration.database);
+            println!(
+                "{:<12} {:<24} {:>5} {:>8} {:>8} {:>8} {:>8}",
+                "provider", "variable", "lead", "samples", "bias", "mae", "rmse"
+            );
+            for stat in verify_forecasts(&filter, &pool).await? {
+                println!(
+                    "{:<12} {:<24} {:>5} {:>8} {:>8.2} {:>8.2} {:>8.2}",
+                    stat.provider,
+                    stat.variable,
+                    stat.lead_hours,
+                    stat.samples,
+                    stat.bias,
+                    stat.mae,
+                    stat.rmse
+                );
+            }
+        }
+        Command::Export {
+            format,
+            from,
+            to,
+            location,
+            user_id,
+            columns,
+            units,
+            wind_speed_unit,
+            output,
+        } => {
+            let filter =
+                ForecastFilter::parse(&from, &to, &location).map_err(anyhow::Error::msg)?;
+            let format = ExportFormat::try_from(format).map_err(anyhow::Error::msg)?;
+            let wind_speed_unit = wind_speed_unit
+                .map(WindSpeedUnit::try_from)
+                .transpose()
+                .map_err(anyhow::Error::msg)?;
+            let units = Units::new(
+                UnitSystem::try_from(units).map_err(anyhow::Error::msg)?,
+                wind_speed_unit,
+            );
+            let export = ForecastExport::new(format, columns.as_deref(), units)
+                .map_err(anyhow::Error::msg)?;
+            let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(match output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout().lock()),
+            });
+            if let Some(header) = export.header() {
+                writer.write_all(header.as_bytes())?;
+            }
+            let pool = get_connection_pool(configuration.database);
+            let mut rows = stream_forecasts(user_id, None, filter, pool);
+            while let Some(row) = rows.next().await {
+                writer.write_all(export.line(row?).as_bytes())?;
+            }
+            writer.flush()?;
+        }
+        Command::LoadGazetteer { path, admin1 } => {
+            let pool = get_connection_pool(configuration.database);
+            load_gazetteer(&path, &pool).await?;
+            if let Some(admin1) = admin1 {
+                load_admin1_codes(&admin1, &pool).await?;
+            }
+        }
+    }
     Ok(())
 }
 
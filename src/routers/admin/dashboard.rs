@@ -4,6 +4,7 @@ use axum::{
 };
 use axum_messages::Messages;
 use sqlx::PgPool;
+use std::fmt::Write;
 use thiserror::Error;
 use tower_sessions::{session, Session};
 use uuid::Uuid;
@@ -28,27 +29,36 @@ impl From<DashboardError> for Redirect {
     }
 }
 
-pub async fn admin_dashboard(
-    State(state): State<AppState>,
-    session: Session,
-) -> Result<Response, Redirect> {
-    let user_data: UserData = session
+pub async fn get_session_user(session: &Session) -> Result<UserData, DashboardError> {
+    session
         .get("user.data")
         .await
         .map_err(|e| {
             DashboardError::InvalidSessionData(format!("Session query error, details: {}", e))
         })?
-        .ok_or(DashboardError::SessionNotFound(format!(
-            "User session data not found"
-        )))?;
+        .ok_or(DashboardError::SessionNotFound(
+            "User session data not found".to_string(),
+        ))
+}
+
+pub async fn admin_dashboard(
+    State(state): State<AppState>,
+    session: Session,
+    flash_message: Messages,
+) -> Result<Response, Redirect> {
+    let user_data = get_session_user(&session).await?;
 
     let user_id = Uuid::parse_str(&user_data.user_id).map_err(DashboardError::UuidParseError)?;
     let user_name = user_data.user_name;
     let token = get_token_value(user_id, &state.connect_pool).await?;
-    Ok(render_dashboard(&user_name, &token).into_response())
+    let mut message_html = String::new();
+    for fm in flash_message {
+        writeln!(message_html, "{}", fm.message).unwrap();
+    }
+    Ok(render_dashboard(&user_name, &token, &message_html).into_response())
 }
 
-fn render_dashboard(user_name: &str, token: &str) -> Html<String> {
+fn render_dashboard(user_name: &str, token: &str, message_html: &str) -> Html<String> {
     Html(
         format!(
             r#"<!DOCTYPE html>
@@ -60,6 +70,7 @@ fn render_dashboard(user_name: &str, token: &str) -> Html<String> {
 </head>
 
 <body>
+{}
 <p>Welcome {}!</p>
 <p>Available actions:</p>
 <ol>
@@ -71,11 +82,20 @@ fn render_dashboard(user_name: &str, token: &str) -> Html<String> {
 <li>
     <p>Your token:{}</p>    
 </li>    
+<li>
+    <form name="reprocessForm" action="/admin/reprocess" method="post">
+        <p>Reprocess archived forecasts (UTC, empty means unbounded)</p>
+        <label>From <input type="datetime-local" name="from"></label>
+        <label>To <input type="datetime-local" name="to"></label>
+        <label>Location <input type="text" placeholder="lat,lon" name="location"></label>
+        <input type="submit" value="Reprocess">
+    </form>
+</li>
 </ol>
 </body>
 
 </html>"#,
-            user_name, token
+            message_html, user_name, token
         )
         .to_string(),
     )
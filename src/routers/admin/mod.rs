@@ -1,3 +1,5 @@
 mod dashboard;
+mod reprocess;
 
 pub use dashboard::{admin_dashboard, log_out};
+pub use reprocess::admin_reprocess;
@@ -0,0 +1,54 @@
+use axum::{extract::State, response::Redirect, Form};
+use axum_messages::Messages;
+use serde::Deserialize;
+use tower_sessions::Session;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    routers::{reprocess_raw_forecasts, ForecastFilter},
+    start_up::AppState,
+};
+
+use super::dashboard::{get_session_user, DashboardError};
+
+#[derive(Deserialize)]
+pub struct ReprocessForm {
+    from: String,
+    to: String,
+    location: String,
+}
+
+/// Reprocesses the archived responses of the logged in user only, the admin
+/// dashboard being available to every account.
+#[tracing::instrument(name = "Admin reprocess", skip(state, session, messages, form))]
+pub async fn admin_reprocess(
+    State(state): State<AppState>,
+    session: Session,
+    messages: Messages,
+    Form(form): Form<ReprocessForm>,
+) -> Result<Redirect, Redirect> {
+    let user_data = get_session_user(&session).await?;
+    let user_id = Uuid::parse_str(&user_data.user_id).map_err(DashboardError::UuidParseError)?;
+    let filter = match ForecastFilter::parse(&form.from, &form.to, &form.location) {
+        Ok(filter) => filter,
+        Err(e) => {
+            messages.error(format!("<p><i>{}</i></p>", htmlescape::encode_minimal(&e)));
+            return Ok(Redirect::to("/admin/dashboard"));
+        }
+    };
+    match reprocess_raw_forecasts(Some(user_id), &filter, &state.connect_pool).await {
+        Ok(summary) => messages.info(format!(
+            "<p><i>Reprocessed {} archived forecasts, {} failed.</i></p>",
+            summary.processed, summary.failed
+        )),
+        Err(e) => {
+            error!("Failed to reprocess archived forecasts, details: {}", e);
+            messages.error(format!(
+                "<p><i>{}</i></p>",
+                htmlescape::encode_minimal(&e.to_string())
+            ))
+        }
+    };
+    Ok(Redirect::to("/admin/dashboard"))
+}
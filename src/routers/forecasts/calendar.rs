@@ -0,0 +1,192 @@
+use axum::{
+    extract::{rejection::QueryRejection, Path, Query, State},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+};
+use chrono::{Days, NaiveDate, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{authentication::ApiUser, routers::get_subscribed_location, start_up::AppState};
+
+use super::{
+    list::ForecastApiError,
+    store::{daily_summaries, DailySummary},
+    units::{UnitSystem, Units},
+};
+
+/// Daily precipitation from which a day counts as wet, in mm.
+const WET_DAY_MM: f64 = 1.0;
+const DEFAULT_DAYS: i32 = 7;
+const MAX_DAYS: i32 = 16;
+/// RFC 5545 limit of a content line in octets, excluding the line break.
+const MAX_LINE_OCTETS: usize = 75;
+
+#[derive(Deserialize, Debug)]
+pub struct CalendarQuery {
+    days: Option<i32>,
+    /// Unit system of temperatures and precipitation; metric by default.
+    #[serde(default)]
+    units: UnitSystem,
+}
+
+/// Serves `/calendar/<token>/<location_id>.ics` as an iCalendar feed with an
+/// all-day event per day, such as `☀ 12–21°C, 10% 降水`. Calendar apps cannot
+/// send a bearer token, so the API token is part of the subscription URL.
+#[tracing::instrument(name = "Forecast calendar", skip(state, token))]
+pub async fn forecast_calendar(
+    State(state): State<AppState>,
+    Path((token, file)): Path<(String, String)>,
+    query: Result<Query<CalendarQuery>, QueryRejection>,
+) -> Result<Response, ForecastApiError> {
+    let Query(query) = query?;
+    let pool = &state.connect_pool;
+    let user = ApiUser::from_token(&token, pool).await?;
+    let location_id = file
+        .strip_suffix(".ics")
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or(ForecastApiError::NotFound)?;
+    let days = query.days.unwrap_or(DEFAULT_DAYS);
+    if !(1..=MAX_DAYS).contains(&days) {
+        return Err(ForecastApiError::ValidationError(format!(
+            "days must be between 1 and {}",
+            MAX_DAYS
+        )));
+    }
+
+    let location = get_subscribed_location(&user.user_id, &location_id, pool)
+        .await?
+        .ok_or(ForecastApiError::NotFound)?;
+    let tz = location.tz();
+    let summaries = daily_summaries(&user.user_id, &location, tz.name(), days, pool).await?;
+
+    let dtstamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//weather-forecast-wechat-bot//Forecast Calendar//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!(
+            "X-WR-CALNAME:{}",
+            escape_text(&format!("{} 天气预报", location.name))
+        ),
+        format!("X-WR-TIMEZONE:{}", escape_text(tz.name())),
+    ];
+    for summary in &summaries {
+        let next_day = summary.day + Days::new(1);
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!(
+                "UID:{}-{}@weather-forecast-wechat-bot",
+                location.id,
+                ics_date(summary.day)
+            ),
+            format!("DTSTAMP:{}", dtstamp),
+            format!("DTSTART;VALUE=DATE:{}", ics_date(summary.day)),
+            format!("DTEND;VALUE=DATE:{}", ics_date(next_day)),
+            format!(
+                "SUMMARY:{}",
+                escape_text(&event_summary(summary, query.units))
+            ),
+            "TRANSP:TRANSPARENT".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let body: String = lines.iter().map(|line| fold_line(line)).collect();
+    Ok(([(CONTENT_TYPE, "text/calendar; charset=utf-8")], body).into_response())
+}
+
+/// Picks an icon from provider independent variables, since every provider
+/// numbers its weather codes differently. Days beyond the hourly forecast
+/// have no cloud cover, so a dry one gets no icon.
+fn weather_icon(summary: &DailySummary) -> Option<&'static str> {
+    let positive = |value: Option<f64>| value.is_some_and(|value| value > 0.0);
+    let wet = summary
+        .precipitation_accumulation
+        .is_some_and(|accumulation| accumulation >= WET_DAY_MM);
+    let freezing = summary.temperature_max.is_some_and(|max| max <= 0.0);
+    if positive(summary.snow_intensity) || (wet && freezing) {
+        Some("❄")
+    } else if wet
+        || positive(summary.rain_intensity)
+        || summary.precipitation_probability.unwrap_or_default() >= 50.0
+    {
+        Some("🌧")
+    } else {
+        match summary.cloud_cover? {
+            cloud_cover if cloud_cover >= 70.0 => Some("☁"),
+            cloud_cover if cloud_cover >= 30.0 => Some("⛅"),
+            _ => Some("☀"),
+        }
+    }
+}
+
+/// `summary` is metric and converted to `units` only for display, so the
+/// icon thresholds stay in °C and mm.
+fn event_summary(summary: &DailySummary, units: UnitSystem) -> String {
+    let mut parts = Vec::new();
+    if let (Some(min), Some(max)) = (summary.temperature_min, summary.temperature_max) {
+        parts.push(format!(
+            "{:.0}–{:.0}{}",
+            units.temperature(min),
+            units.temperature(max),
+            Units::new(units, None).labels().temperature
+        ));
+    }
+    if let Some(probability) = summary.precipitation_probability {
+        parts.push(format!("{:.0}% 降水", probability));
+    } else if let Some(accumulation) = summary.precipitation_accumulation {
+        parts.push(format!(
+            "{:.1} {} 降水",
+            units.precipitation(accumulation),
+            units.accumulation_label()
+        ));
+    }
+    let mut text = parts.join(", ");
+    if let Some(icon) = weather_icon(summary) {
+        text = format!("{} {}", icon, text).trim_end().to_string();
+    }
+    text
+}
+
+fn ics_date(day: NaiveDate) -> String {
+    day.format("%Y%m%d").to_string()
+}
+
+/// Escapes a TEXT value as RFC 5545 section 3.3.11 requires.
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Ends a content line with CRLF, folding it into continuation lines that
+/// start with a space when it is longer than 75 octets. Folds never split a
+/// UTF-8 character.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
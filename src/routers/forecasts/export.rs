@@ -0,0 +1,214 @@
+use axum::{
+    body::Body,
+    extract::{rejection::QueryRejection, Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+};
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{authentication::ApiUser, routers::get_subscribed_location, start_up::AppState};
+
+use super::{
+    list::{forecast_filter, ForecastApiError},
+    store::{stream_forecasts, StoredForecast, FORECAST_FIELDS, ROW_KEYS},
+    units::{UnitSystem, Units, WindSpeedUnit},
+};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// Newline-delimited JSON, one object per row.
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+impl TryFrom<String> for ExportFormat {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "ndjson" => Ok(Self::Ndjson),
+            other => Err(format!(
+                "{} is not a supported export format. Use either `csv` or `ndjson`.",
+                other
+            )),
+        }
+    }
+}
+
+/// Turns stored forecasts into CSV or NDJSON lines with the selected
+/// columns, in the selected order and unit system.
+pub struct ForecastExport {
+    format: ExportFormat,
+    columns: Vec<&'static str>,
+    units: Units,
+}
+
+impl ForecastExport {
+    /// `columns` is comma separated; every column is exported when it is
+    /// missing.
+    pub fn new(format: ExportFormat, columns: Option<&str>, units: Units) -> Result<Self, String> {
+        let all_columns = ROW_KEYS.iter().chain(FORECAST_FIELDS.iter());
+        let columns = match columns {
+            None => all_columns.copied().collect(),
+            Some(columns) => columns
+                .split(',')
+                .map(str::trim)
+                .filter(|column| !column.is_empty())
+                .map(|column| {
+                    all_columns
+                        .clone()
+                        .find(|known| **known == column)
+                        .copied()
+                        .ok_or_else(|| format!("Unknown column {}", column))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        if columns.is_empty() {
+            return Err("No columns selected".to_string());
+        }
+        Ok(ForecastExport {
+            format,
+            columns,
+            units,
+        })
+    }
+
+    /// The CSV header line; NDJSON has none.
+    pub fn header(&self) -> Option<String> {
+        match self.format {
+            ExportFormat::Csv => Some(format!("{}\n", self.columns.join(","))),
+            ExportFormat::Ndjson => None,
+        }
+    }
+
+    pub fn line(&self, mut forecast: StoredForecast) -> String {
+        self.units.convert(&mut forecast);
+        let mut row = forecast.to_row();
+        match self.format {
+            ExportFormat::Csv => {
+                let cells: Vec<String> = self
+                    .columns
+                    .iter()
+                    .map(|column| csv_cell(row.get(*column).unwrap_or(&Value::Null)))
+                    .collect();
+                format!("{}\n", cells.join(","))
+            }
+            ExportFormat::Ndjson => {
+                let selected: Map<String, Value> = self
+                    .columns
+                    .iter()
+                    .map(|column| {
+                        let value = row.remove(*column).unwrap_or(Value::Null);
+                        (column.to_string(), value)
+                    })
+                    .collect();
+                format!("{}\n", Value::Object(selected))
+            }
+        }
+    }
+}
+
+/// Quotes a cell when it contains a separator, quote or line break, as
+/// RFC 4180 requires. Text starting like a spreadsheet formula, e.g. a city
+/// name of `=HYPERLINK(...)`, gets a leading `'` so it is shown, not run;
+/// numbers such as negative temperatures are left alone.
+fn csv_cell(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(text) if text.starts_with(['=', '+', '-', '@', '\t', '\r']) => {
+            format!("'{}", text)
+        }
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+    location_id: Option<Uuid>,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    from: Option<String>,
+    to: Option<String>,
+    /// Comma separated columns, all of them when missing.
+    columns: Option<String>,
+    #[serde(default)]
+    units: UnitSystem,
+    /// Overrides the wind speed unit of `units`.
+    wind_speed_unit: Option<WindSpeedUnit>,
+}
+
+/// Downloads every stored forecast of the user matching the query, every
+/// issued version included, as CSV or NDJSON. Rows are streamed from the
+/// database as they are written to the response.
+#[tracing::instrument(name = "Export forecasts", skip(state, user))]
+pub async fn export_forecasts(
+    State(state): State<AppState>,
+    user: ApiUser,
+    query: Result<Query<ExportQuery>, QueryRejection>,
+) -> Result<Response, ForecastApiError> {
+    let Query(query) = query?;
+    let filter = forecast_filter(
+        query.lat,
+        query.lon,
+        query.from.as_deref(),
+        query.to.as_deref(),
+    )?;
+    let units = Units::new(query.units, query.wind_speed_unit);
+    let export = ForecastExport::new(query.format, query.columns.as_deref(), units)
+        .map_err(ForecastApiError::ValidationError)?;
+    let pool = state.connect_pool.clone();
+    if let Some(location_id) = query.location_id {
+        get_subscribed_location(&user.user_id, &location_id, &pool)
+            .await?
+            .ok_or(ForecastApiError::NotFound)?;
+    }
+
+    let header = stream::iter(export.header().map(Ok));
+    let rows = stream_forecasts(user.user_id, query.location_id, filter, pool).map(move |row| {
+        row.map(|forecast| export.line(forecast)).inspect_err(|e| {
+            error!("Forecast export failed, details: {}", e);
+        })
+    });
+    let headers = [
+        (CONTENT_TYPE, query.format.content_type().to_string()),
+        (
+            CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"forecasts.{}\"",
+                query.format.extension()
+            ),
+        ),
+    ];
+    Ok((headers, Body::from_stream(header.chain(rows))).into_response())
+}
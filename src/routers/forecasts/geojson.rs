@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DurationRound, TimeDelta, Utc};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    authentication::ApiUser,
+    routers::{list_subscribed_locations, parse_time, Location},
+    start_up::AppState,
+};
+
+use super::{
+    list::{parse_fields, ForecastApiError},
+    store::{forecasts_at, StoredForecast},
+    units::{UnitSystem, Units, WindSpeedUnit},
+};
+
+/// Forecast properties every feature with a forecast carries.
+const FORECAST_KEYS: [&str; 4] = [
+    "forecast_time",
+    "forecast_time_local",
+    "forecast_issued_at",
+    "provider",
+];
+
+#[derive(Deserialize, Debug)]
+pub struct GeoJsonQuery {
+    /// Hour to show, truncated to the hour; the current hour by default.
+    time: Option<String>,
+    /// Comma separated forecast variables, all of them when missing.
+    fields: Option<String>,
+    #[serde(default)]
+    units: UnitSystem,
+    /// Overrides the wind speed unit of `units`.
+    wind_speed_unit: Option<WindSpeedUnit>,
+}
+
+/// Renders every location of the user as a GeoJSON Point feature with the
+/// latest forecast for the requested hour as properties. Locations without
+/// a forecast for that hour are included with a null `forecast_time`.
+#[tracing::instrument(name = "Forecasts GeoJSON", skip(state, user))]
+pub async fn forecasts_geojson(
+    State(state): State<AppState>,
+    user: ApiUser,
+    query: Result<Query<GeoJsonQuery>, QueryRejection>,
+) -> Result<Response, ForecastApiError> {
+    let Query(query) = query?;
+    let time = parse_time(query.time.as_deref().unwrap_or_default())
+        .map_err(ForecastApiError::ValidationError)?
+        .unwrap_or_else(Utc::now);
+    let hour = time
+        .duration_trunc(TimeDelta::hours(1))
+        .map_err(|e| ForecastApiError::ValidationError(e.to_string()))?;
+    let fields = parse_fields(query.fields.as_deref())?;
+    let units = Units::new(query.units, query.wind_speed_unit);
+    let pool = &state.connect_pool;
+
+    let locations = list_subscribed_locations(&user.user_id, pool).await?;
+    let mut forecasts: HashMap<_, _> = forecasts_at(&user.user_id, hour, pool)
+        .await?
+        .into_iter()
+        .filter_map(|forecast| forecast.location_id.map(|id| (id, forecast)))
+        .collect();
+    let features: Vec<Value> = locations
+        .into_iter()
+        .map(|location| {
+            let forecast = forecasts.remove(&location.id);
+            feature(location, forecast, &fields, &units)
+        })
+        .collect();
+    let collection = json!({
+        "type": "FeatureCollection",
+        "features": features,
+        "units": units.labels(),
+    });
+    Ok(([(CONTENT_TYPE, "application/geo+json")], Json(collection)).into_response())
+}
+
+fn feature(
+    location: Location,
+    forecast: Option<StoredForecast>,
+    fields: &[&str],
+    units: &Units,
+) -> Value {
+    let mut properties = Map::new();
+    properties.insert("location_id".to_string(), json!(location.id));
+    properties.insert("name".to_string(), json!(location.name));
+    properties.insert("country".to_string(), json!(location.country));
+    properties.insert("admin_region".to_string(), json!(location.admin_region));
+    properties.insert("timezone".to_string(), json!(location.tz().name()));
+    match forecast.map(|mut forecast| {
+        units.convert(&mut forecast);
+        forecast.to_row()
+    }) {
+        Some(forecast) => properties.extend(forecast.into_iter().filter(|(key, _)| {
+            FORECAST_KEYS.contains(&key.as_str()) || fields.contains(&key.as_str())
+        })),
+        None => {
+            properties.insert("forecast_time".to_string(), Value::Null);
+        }
+    }
+    json!({
+        "type": "Feature",
+        "id": location.id,
+        // GeoJSON orders positions longitude first.
+        "geometry": {
+            "type": "Point",
+            "coordinates": [location.longitude, location.latitude],
+        },
+        "properties": properties,
+    })
+}
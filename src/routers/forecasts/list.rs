@@ -0,0 +1,265 @@
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    authentication::{ApiAuthError, ApiUser},
+    errors::DbError,
+    geocoding::local_day_bounds,
+    routers::{get_subscribed_location, ForecastFilter, Location},
+    start_up::AppState,
+    weather_client::Coordinate,
+};
+
+use super::{
+    store::{query_forecasts, StoredForecast, FORECAST_FIELDS, ROW_KEYS},
+    units::{UnitLabels, UnitSystem, Units, WindSpeedUnit},
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[derive(Error, Debug)]
+pub enum ForecastApiError {
+    #[error("Invalid query: {0}")]
+    QueryError(#[from] QueryRejection),
+    #[error("Validation error: {0}")]
+    ValidationError(String),
+    #[error("Location not found")]
+    NotFound,
+    #[error("No stored location within {0} km")]
+    NoLocationNearby(f64),
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] DbError),
+    #[error(transparent)]
+    AuthError(#[from] ApiAuthError),
+}
+
+impl IntoResponse for ForecastApiError {
+    fn into_response(self) -> Response {
+        let content = self.to_string();
+        let (status_code, status) = match self {
+            ForecastApiError::AuthError(e) => return e.into_response(),
+            ForecastApiError::QueryError(_) | ForecastApiError::ValidationError(_) => {
+                (StatusCode::BAD_REQUEST, "VALIDATION_ERROR")
+            }
+            ForecastApiError::NotFound | ForecastApiError::NoLocationNearby(_) => {
+                (StatusCode::NOT_FOUND, "NOT_FOUND")
+            }
+            ForecastApiError::DatabaseError(e) => {
+                error!("Forecast request failed, details: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "DATABASE_ERROR")
+            }
+        };
+        let body = Json(json!({"status": status, "content": content}));
+        (status_code, body).into_response()
+    }
+}
+
+/// Selects stored forecasts by subscribed location or by the exact stored
+/// coordinate, and by forecast time in `[from, to)`.
+#[derive(Deserialize, Debug)]
+pub struct ForecastQuery {
+    location_id: Option<Uuid>,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    from: Option<String>,
+    to: Option<String>,
+    /// A day of the location's timezone instead of `from` and `to`; needs
+    /// `location_id`.
+    date: Option<NaiveDate>,
+    /// Comma separated forecast variables, all of `FORECAST_FIELDS` when
+    /// missing.
+    fields: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    #[serde(default)]
+    units: UnitSystem,
+    /// Overrides the wind speed unit of `units`.
+    wind_speed_unit: Option<WindSpeedUnit>,
+}
+
+#[derive(Serialize)]
+pub struct ForecastPage {
+    data: Vec<Value>,
+    units: UnitLabels,
+    pagination: Pagination,
+}
+
+#[derive(Serialize)]
+pub struct Pagination {
+    limit: i64,
+    offset: i64,
+    total: i64,
+    /// `offset` of the next page, absent on the last page.
+    next_offset: Option<i64>,
+}
+
+impl ForecastQuery {
+    fn filter(&self) -> Result<ForecastFilter, ForecastApiError> {
+        forecast_filter(self.lat, self.lon, self.from.as_deref(), self.to.as_deref())
+    }
+
+    fn fields(&self) -> Result<Vec<&str>, ForecastApiError> {
+        parse_fields(self.fields.as_deref())
+    }
+
+    fn page(&self) -> Result<(i64, i64), ForecastApiError> {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        let offset = self.offset.unwrap_or(0);
+        if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+            return Err(ForecastApiError::ValidationError(format!(
+                "limit must be between 1 and {}",
+                MAX_PAGE_SIZE
+            )));
+        }
+        if offset < 0 {
+            return Err(ForecastApiError::ValidationError(
+                "offset must not be negative".to_string(),
+            ));
+        }
+        Ok((limit, offset))
+    }
+}
+
+/// Builds a filter from the `lat`, `lon`, `from` and `to` query parameters.
+/// The coordinate is snapped like stored coordinates.
+pub(super) fn forecast_filter(
+    lat: Option<f64>,
+    lon: Option<f64>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<ForecastFilter, ForecastApiError> {
+    let mut filter = ForecastFilter::parse(from.unwrap_or_default(), to.unwrap_or_default(), "")
+        .map_err(ForecastApiError::ValidationError)?;
+    filter.location = match (lat, lon) {
+        (Some(latitude), Some(longitude)) => Some(
+            Coordinate::parse(format!("{},{}", latitude, longitude))
+                .map_err(|e| ForecastApiError::ValidationError(e.to_string()))?
+                .snapped(),
+        ),
+        (None, None) => None,
+        _ => {
+            return Err(ForecastApiError::ValidationError(
+                "lat and lon must be given together".to_string(),
+            ))
+        }
+    };
+    Ok(filter)
+}
+
+/// Narrows the window to the local day `date` of `location`, from its
+/// midnight to the next one however long that day is there.
+pub(super) fn local_date_filter(
+    mut filter: ForecastFilter,
+    date: Option<NaiveDate>,
+    location: Option<&Location>,
+) -> Result<ForecastFilter, ForecastApiError> {
+    let Some(date) = date else {
+        return Ok(filter);
+    };
+    if filter.from.is_some() || filter.to.is_some() {
+        return Err(ForecastApiError::ValidationError(
+            "date cannot be combined with from or to".to_string(),
+        ));
+    }
+    let location = location
+        .ok_or_else(|| ForecastApiError::ValidationError("date needs a location_id".to_string()))?;
+    let (from, to) = local_day_bounds(date, location.tz());
+    filter.from = Some(from);
+    filter.to = Some(to);
+    Ok(filter)
+}
+
+/// Parses a comma separated `fields` parameter, selecting every variable
+/// when it is missing.
+pub(super) fn parse_fields(fields: Option<&str>) -> Result<Vec<&str>, ForecastApiError> {
+    let Some(fields) = fields else {
+        return Ok(FORECAST_FIELDS.to_vec());
+    };
+    fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            FORECAST_FIELDS
+                .contains(&field)
+                .then_some(field)
+                .ok_or_else(|| {
+                    ForecastApiError::ValidationError(format!(
+                        "Unknown field {}, expected some of {}",
+                        field,
+                        FORECAST_FIELDS.join(",")
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Serializes a forecast in `units` with only the selected variables
+/// besides the keys every row has.
+pub(super) fn select_fields(mut forecast: StoredForecast, fields: &[&str], units: &Units) -> Value {
+    units.convert(&mut forecast);
+    let mut row = forecast.to_row();
+    row.retain(|key, _| ROW_KEYS.contains(&key.as_str()) || fields.contains(&key.as_str()));
+    Value::Object(row)
+}
+
+/// Lists the hourly forecasts stored for the user, every issued version
+/// included, one page at a time.
+#[tracing::instrument(name = "List forecasts", skip(state, user))]
+pub async fn list_forecasts(
+    State(state): State<AppState>,
+    user: ApiUser,
+    query: Result<Query<ForecastQuery>, QueryRejection>,
+) -> Result<Json<ForecastPage>, ForecastApiError> {
+    let Query(query) = query?;
+    let filter = query.filter()?;
+    let fields = query.fields()?;
+    let (limit, offset) = query.page()?;
+    let units = Units::new(query.units, query.wind_speed_unit);
+    let pool = &state.connect_pool;
+    let location = match query.location_id {
+        Some(location_id) => Some(
+            get_subscribed_location(&user.user_id, &location_id, pool)
+                .await?
+                .ok_or(ForecastApiError::NotFound)?,
+        ),
+        None => None,
+    };
+    let filter = local_date_filter(filter, query.date, location.as_ref())?;
+
+    let (forecasts, total) = query_forecasts(
+        &user.user_id,
+        query.location_id,
+        &filter,
+        limit,
+        offset,
+        pool,
+    )
+    .await?;
+    let next_offset = (offset + limit < total).then_some(offset + limit);
+    let data = forecasts
+        .into_iter()
+        .map(|forecast| select_fields(forecast, &fields, &units))
+        .collect();
+    Ok(Json(ForecastPage {
+        data,
+        units: units.labels(),
+        pagination: Pagination {
+            limit,
+            offset,
+            total,
+            next_offset,
+        },
+    }))
+}
@@ -0,0 +1,18 @@
+mod calendar;
+mod export;
+mod geojson;
+mod list;
+mod nearest;
+mod store;
+mod units;
+
+pub use calendar::forecast_calendar;
+pub use export::{export_forecasts, ExportFormat, ForecastExport};
+pub use geojson::forecasts_geojson;
+pub use list::{list_forecasts, ForecastApiError};
+pub use nearest::nearest_forecast;
+pub use store::{
+    forecasts_at, latest_forecasts, query_forecasts, stream_forecasts, StoredForecast,
+    FORECAST_FIELDS,
+};
+pub use units::{UnitLabels, UnitSystem, Units, WindSpeedUnit};
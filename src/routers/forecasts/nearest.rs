@@ -0,0 +1,98 @@
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    Json,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    authentication::ApiUser,
+    routers::{nearest_subscribed_location, parse_time, ForecastFilter, Location},
+    start_up::AppState,
+    weather_client::Coordinate,
+};
+
+use super::{
+    list::{local_date_filter, parse_fields, select_fields, ForecastApiError},
+    store::latest_forecasts,
+    units::{UnitLabels, UnitSystem, Units, WindSpeedUnit},
+};
+
+/// A coordinate that need not match a stored one exactly.
+#[derive(Deserialize, Debug)]
+pub struct NearestForecastQuery {
+    lat: f64,
+    lon: f64,
+    /// Defaults to `forecasts.default_radius_km` of the configuration.
+    radius_km: Option<f64>,
+    from: Option<String>,
+    to: Option<String>,
+    /// Only forecasts issued by then, to see how a forecast drifted.
+    as_of: Option<String>,
+    /// A day of the found location's timezone instead of `from` and `to`.
+    date: Option<NaiveDate>,
+    fields: Option<String>,
+    #[serde(default)]
+    units: UnitSystem,
+    /// Overrides the wind speed unit of `units`.
+    wind_speed_unit: Option<WindSpeedUnit>,
+}
+
+#[derive(Serialize)]
+pub struct NearestForecast {
+    location: Location,
+    distance_km: f64,
+    data: Vec<Value>,
+    units: UnitLabels,
+}
+
+/// Returns the latest forecast of the subscribed location nearest to the
+/// given coordinate, so `39.9041,116.4074` finds data stored for
+/// `39.9042,116.4074`.
+#[tracing::instrument(name = "Nearest forecast", skip(state, user))]
+pub async fn nearest_forecast(
+    State(state): State<AppState>,
+    user: ApiUser,
+    query: Result<Query<NearestForecastQuery>, QueryRejection>,
+) -> Result<Json<NearestForecast>, ForecastApiError> {
+    let Query(query) = query?;
+    let settings = &state.forecast_settings;
+    let radius_km = query.radius_km.unwrap_or(settings.default_radius_km);
+    if !(radius_km > 0.0 && radius_km <= settings.max_radius_km) {
+        return Err(ForecastApiError::ValidationError(format!(
+            "radius_km must be greater than 0 and at most {}",
+            settings.max_radius_km
+        )));
+    }
+    let coordinate = Coordinate::parse(format!("{},{}", query.lat, query.lon))
+        .map_err(|e| ForecastApiError::ValidationError(e.to_string()))?;
+    let filter = ForecastFilter::parse(
+        query.from.as_deref().unwrap_or_default(),
+        query.to.as_deref().unwrap_or_default(),
+        "",
+    )
+    .map_err(ForecastApiError::ValidationError)?;
+    let as_of = parse_time(query.as_of.as_deref().unwrap_or_default())
+        .map_err(ForecastApiError::ValidationError)?;
+    let fields = parse_fields(query.fields.as_deref())?;
+    let units = Units::new(query.units, query.wind_speed_unit);
+    let pool = &state.connect_pool;
+
+    let (location, distance_km) =
+        nearest_subscribed_location(&user.user_id, &coordinate, radius_km, pool)
+            .await?
+            .ok_or(ForecastApiError::NoLocationNearby(radius_km))?;
+    let filter = local_date_filter(filter, query.date, Some(&location))?;
+    let data = latest_forecasts(&user.user_id, &location.id, &filter, as_of, pool)
+        .await?
+        .into_iter()
+        .map(|forecast| select_fields(forecast, &fields, &units))
+        .collect();
+    Ok(Json(NearestForecast {
+        location,
+        distance_km,
+        data,
+        units: units.labels(),
+    }))
+}
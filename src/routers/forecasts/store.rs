@@ -0,0 +1,447 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::{
+    errors::DbError,
+    geocoding::resolve_timezone,
+    routers::{ForecastFilter, Location},
+    weather_client::{Coordinate, Datum},
+};
+
+/// Returned for every row whatever `fields` selects.
+pub const ROW_KEYS: [&str; 9] = [
+    "id",
+    "location_id",
+    "latitude",
+    "longitude",
+    "timezone",
+    "provider",
+    "forecast_time",
+    "forecast_time_local",
+    "forecast_issued_at",
+];
+
+/// Rows read ahead of a slow export consumer.
+const EXPORT_BUFFER_ROWS: usize = 256;
+
+/// Forecast variables a client can select with `fields`.
+pub const FORECAST_FIELDS: [&str; 20] = [
+    "precipitation_probability",
+    "rain_intensity",
+    "freezing_rain_intensity",
+    "sleet_intensity",
+    "snow_intensity",
+    "temperature",
+    "temperature_apparent",
+    "dew_point",
+    "humidity",
+    "wind_speed",
+    "wind_direction",
+    "wind_gust",
+    "pressure_surface_level",
+    "uv_index",
+    "visibility",
+    "cloud_cover",
+    "cloud_base",
+    "cloud_ceiling",
+    "weather_code",
+    "city_name",
+];
+
+/// One stored `weather_info` row, i.e. one hourly forecast as issued at
+/// `forecast_issued_at`.
+#[derive(Serialize, Debug)]
+pub struct StoredForecast {
+    pub id: Uuid,
+    pub location_id: Option<Uuid>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub city_name: Option<String>,
+    pub provider: Option<String>,
+    pub forecast_time: DateTime<Utc>,
+    pub forecast_issued_at: DateTime<Utc>,
+    pub precipitation_probability: Option<f64>,
+    pub rain_intensity: Option<f64>,
+    pub freezing_rain_intensity: Option<f64>,
+    pub sleet_intensity: Option<f64>,
+    pub snow_intensity: Option<f64>,
+    pub temperature: Option<f64>,
+    pub temperature_apparent: Option<f64>,
+    pub dew_point: Option<f64>,
+    pub humidity: Option<f64>,
+    pub wind_speed: Option<f64>,
+    pub wind_direction: Option<f64>,
+    pub wind_gust: Option<f64>,
+    pub pressure_surface_level: Option<f64>,
+    pub uv_index: Option<f64>,
+    pub visibility: Option<f64>,
+    pub cloud_cover: Option<f64>,
+    pub cloud_base: Option<f64>,
+    pub cloud_ceiling: Option<f64>,
+    pub weather_code: Option<i32>,
+    /// Timezone stored for the location, if any.
+    pub timezone: Option<String>,
+    /// Unit system the values are stored in; responses name theirs once.
+    #[serde(skip)]
+    pub units: String,
+}
+
+impl StoredForecast {
+    /// The timezone of the location, or the one at the forecast coordinate
+    /// when the location has none stored.
+    pub fn tz(&self) -> Tz {
+        let coordinate = Coordinate {
+            latitude: self.latitude,
+            longitude: self.longitude,
+            datum: Datum::Wgs84,
+        };
+        resolve_timezone(self.timezone.as_deref(), &coordinate)
+    }
+
+    /// Serializes the forecast with its resolved `timezone` and the forecast
+    /// time in it as `forecast_time_local`, e.g. `2024-11-01T08:00:00+08:00`.
+    pub fn to_row(&self) -> Map<String, Value> {
+        let tz = self.tz();
+        let Value::Object(mut row) = json!(self) else {
+            unreachable!("StoredForecast serializes to an object");
+        };
+        row.insert("timezone".to_string(), json!(tz.name()));
+        row.insert(
+            "forecast_time_local".to_string(),
+            json!(self.forecast_time.with_timezone(&tz).to_rfc3339()),
+        );
+        row
+    }
+}
+
+/// Returns one page of the forecasts stored for `user_id`, ordered by
+/// forecast time and then issue time, together with the number of matching
+/// rows. The filter window applies to forecast time.
+#[tracing::instrument(name = "Query forecasts", skip(filter, pool))]
+pub async fn query_forecasts(
+    user_id: &Uuid,
+    location_id: Option<Uuid>,
+    filter: &ForecastFilter,
+    limit: i64,
+    offset: i64,
+    pool: &PgPool,
+) -> Result<(Vec<StoredForecast>, i64), DbError> {
+    let latitude = filter.location.as_ref().map(|location| location.latitude);
+    let longitude = filter.location.as_ref().map(|location| location.longitude);
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM weather_info
+        WHERE user_id = $1
+            AND ($2::UUID IS NULL OR location_id = $2)
+            AND ($3::FLOAT IS NULL OR (latitude = $3 AND longitude = $4::FLOAT))
+            AND ($5::TIMESTAMPTZ IS NULL OR (forecast_time AT TIME ZONE 'UTC') >= $5)
+            AND ($6::TIMESTAMPTZ IS NULL OR (forecast_time AT TIME ZONE 'UTC') < $6)
+        "#,
+        user_id,
+        location_id,
+        latitude,
+        longitude,
+        filter.from,
+        filter.to,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let forecasts = sqlx::query_as!(
+        StoredForecast,
+        r#"
+        SELECT id, location_id, latitude, longitude, city_name, provider,
+            forecast_time AT TIME ZONE 'UTC' AS "forecast_time!", forecast_issued_at,
+            precipitation_probability, rain_intensity, freezing_rain_intensity, sleet_intensity, snow_intensity,
+            temperature, temperature_apparent, dew_point, humidity,
+            wind_speed, wind_direction, wind_gust,
+            pressure_surface_level, uv_index, visibility,
+            cloud_cover, cloud_base, cloud_ceiling, weather_code,
+            (SELECT timezone FROM locations WHERE locations.id = weather_info.location_id) AS timezone,
+            units
+        FROM weather_info
+        WHERE user_id = $1
+            AND ($2::UUID IS NULL OR location_id = $2)
+            AND ($3::FLOAT IS NULL OR (latitude = $3 AND longitude = $4::FLOAT))
+            AND ($5::TIMESTAMPTZ IS NULL OR (forecast_time AT TIME ZONE 'UTC') >= $5)
+            AND ($6::TIMESTAMPTZ IS NULL OR (forecast_time AT TIME ZONE 'UTC') < $6)
+        ORDER BY forecast_time, forecast_issued_at, id
+        LIMIT $7 OFFSET $8
+        "#,
+        user_id,
+        location_id,
+        latitude,
+        longitude,
+        filter.from,
+        filter.to,
+        limit,
+        offset,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok((forecasts, total))
+}
+
+/// Returns the newest issued forecast for every forecast hour stored for
+/// `user_id` at `location_id`, within the filter window. With `as_of`, only
+/// forecasts issued by then count, i.e. what was believed at that moment.
+#[tracing::instrument(name = "Query latest forecasts", skip(filter, pool))]
+pub async fn latest_forecasts(
+    user_id: &Uuid,
+    location_id: &Uuid,
+    filter: &ForecastFilter,
+    as_of: Option<DateTime<Utc>>,
+    pool: &PgPool,
+) -> Result<Vec<StoredForecast>, DbError> {
+    let forecasts = sqlx::query_as!(
+        StoredForecast,
+        r#"
+        SELECT DISTINCT ON (forecast_time)
+            id, location_id, latitude, longitude, city_name, provider,
+            forecast_time AT TIME ZONE 'UTC' AS "forecast_time!", forecast_issued_at,
+            precipitation_probability, rain_intensity, freezing_rain_intensity, sleet_intensity, snow_intensity,
+            temperature, temperature_apparent, dew_point, humidity,
+            wind_speed, wind_direction, wind_gust,
+            pressure_surface_level, uv_index, visibility,
+            cloud_cover, cloud_base, cloud_ceiling, weather_code,
+            (SELECT timezone FROM locations WHERE locations.id = weather_info.location_id) AS timezone,
+            units
+        FROM weather_info
+        WHERE user_id = $1
+            AND location_id = $2
+            AND ($3::TIMESTAMPTZ IS NULL OR (forecast_time AT TIME ZONE 'UTC') >= $3)
+            AND ($4::TIMESTAMPTZ IS NULL OR (forecast_time AT TIME ZONE 'UTC') < $4)
+            AND ($5::TIMESTAMPTZ IS NULL OR forecast_issued_at <= $5)
+        ORDER BY forecast_time, forecast_issued_at DESC
+        "#,
+        user_id,
+        location_id,
+        filter.from,
+        filter.to,
+        as_of,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(forecasts)
+}
+
+/// Streams every forecast of `user_id` matching the filter, ordered by
+/// location, forecast time and issue time. Rows are read from the database
+/// as the stream is consumed rather than collected first.
+pub fn stream_forecasts(
+    user_id: Uuid,
+    location_id: Option<Uuid>,
+    filter: ForecastFilter,
+    pool: PgPool,
+) -> BoxStream<'static, Result<StoredForecast, sqlx::Error>> {
+    let (sender, receiver) = mpsc::channel(EXPORT_BUFFER_ROWS);
+    tokio::spawn(async move {
+        let latitude = filter.location.as_ref().map(|location| location.latitude);
+        let longitude = filter.location.as_ref().map(|location| location.longitude);
+        let mut rows = sqlx::query_as!(
+            StoredForecast,
+            r#"
+            SELECT id, location_id, latitude, longitude, city_name, provider,
+                forecast_time AT TIME ZONE 'UTC' AS "forecast_time!", forecast_issued_at,
+                precipitation_probability, rain_intensity, freezing_rain_intensity, sleet_intensity, snow_intensity,
+                temperature, temperature_apparent, dew_point, humidity,
+                wind_speed, wind_direction, wind_gust,
+                pressure_surface_level, uv_index, visibility,
+                cloud_cover, cloud_base, cloud_ceiling, weather_code,
+                (SELECT timezone FROM locations WHERE locations.id = weather_info.location_id) AS timezone,
+                units
+            FROM weather_info
+            WHERE user_id = $1
+                AND ($2::UUID IS NULL OR location_id = $2)
+                AND ($3::FLOAT IS NULL OR (latitude = $3 AND longitude = $4::FLOAT))
+                AND ($5::TIMESTAMPTZ IS NULL OR (forecast_time AT TIME ZONE 'UTC') >= $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR (forecast_time AT TIME ZONE 'UTC') < $6)
+            ORDER BY latitude, longitude, forecast_time, forecast_issued_at, id
+            "#,
+            user_id,
+            location_id,
+            latitude,
+            longitude,
+            filter.from,
+            filter.to,
+        )
+        .fetch(&pool);
+        while let Some(row) = rows.next().await {
+            // The consumer is gone, e.g. the client closed the download.
+            if sender.send(row).await.is_err() {
+                break;
+            }
+        }
+    });
+    stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|row| (row, receiver))
+    })
+    .boxed()
+}
+
+/// Returns, for every location of `user_id` that has one, the newest issued
+/// forecast for the hour starting at `forecast_time`.
+#[tracing::instrument(name = "Query forecasts at hour", skip(pool))]
+pub async fn forecasts_at(
+    user_id: &Uuid,
+    forecast_time: DateTime<Utc>,
+    pool: &PgPool,
+) -> Result<Vec<StoredForecast>, DbError> {
+    let forecasts = sqlx::query_as!(
+        StoredForecast,
+        r#"
+        SELECT DISTINCT ON (location_id)
+            id, location_id, latitude, longitude, city_name, provider,
+            forecast_time AT TIME ZONE 'UTC' AS "forecast_time!", forecast_issued_at,
+            precipitation_probability, rain_intensity, freezing_rain_intensity, sleet_intensity, snow_intensity,
+            temperature, temperature_apparent, dew_point, humidity,
+            wind_speed, wind_direction, wind_gust,
+            pressure_surface_level, uv_index, visibility,
+            cloud_cover, cloud_base, cloud_ceiling, weather_code,
+            (SELECT timezone FROM locations WHERE locations.id = weather_info.location_id) AS timezone,
+            units
+        FROM weather_info
+        WHERE user_id = $1
+            AND location_id IS NOT NULL
+            AND forecast_time = ($2::TIMESTAMPTZ AT TIME ZONE 'UTC')
+        ORDER BY location_id, forecast_issued_at DESC
+        "#,
+        user_id,
+        forecast_time,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(forecasts)
+}
+
+/// One local day of a location's forecast, in metric units whatever unit
+/// system its rows are stored in.
+#[derive(Debug)]
+pub struct DailySummary {
+    pub day: NaiveDate,
+    pub temperature_min: Option<f64>,
+    pub temperature_max: Option<f64>,
+    /// Liquid-equivalent precipitation over the day in mm, from the daily
+    /// forecast only.
+    pub precipitation_accumulation: Option<f64>,
+    pub precipitation_probability: Option<f64>,
+    pub rain_intensity: Option<f64>,
+    pub snow_intensity: Option<f64>,
+    pub cloud_cover: Option<f64>,
+}
+
+/// Summarizes `days` days of `timezone`, starting today there. An unknown
+/// timezone falls back to UTC.
+///
+/// Days come from the daily forecasts stored for the location, the newest
+/// issue of each local day, with precipitation chance and cloud cover from
+/// the newest hourly forecasts where those reach that day. Only when there
+/// are no daily forecasts for those days are the hourly forecasts
+/// aggregated into days instead. Like `Units::convert`, rows of an unknown
+/// unit system are taken as metric.
+#[tracing::instrument(name = "Query daily forecast summaries", skip(location, pool))]
+pub async fn daily_summaries(
+    user_id: &Uuid,
+    location: &Location,
+    timezone: &str,
+    days: i32,
+    pool: &PgPool,
+) -> Result<Vec<DailySummary>, DbError> {
+    let summaries = sqlx::query_as!(
+        DailySummary,
+        r#"
+        WITH zone AS (
+            SELECT name, (now() AT TIME ZONE name)::DATE AS today
+            FROM (
+                SELECT COALESCE((SELECT name FROM pg_timezone_names WHERE name = $3), 'UTC') AS name
+            ) known
+        ),
+        unit_systems (units, celsius_offset, celsius_scale, millimeters) AS (
+            VALUES ('metric', 0.0::FLOAT, 1.0::FLOAT, 1.0::FLOAT),
+                ('imperial', 32.0, 5.0 / 9.0, 25.4),
+                ('si', 273.15, 1.0, 1.0)
+        ),
+        daily AS (
+            SELECT DISTINCT ON (day) day,
+                (temperature_min - celsius_offset) * celsius_scale AS temperature_min,
+                (temperature_max - celsius_offset) * celsius_scale AS temperature_max,
+                precipitation_accumulation * millimeters AS precipitation_accumulation
+            FROM (
+                SELECT (d.forecast_time AT TIME ZONE 'UTC' AT TIME ZONE zone.name)::DATE AS day,
+                    zone.today, d.*
+                FROM weather_daily d, zone
+                WHERE d.user_id = $1 AND d.latitude = $5 AND d.longitude = $6
+            ) local_daily
+            JOIN unit_systems ON unit_systems.units = CASE WHEN lower(local_daily.units) IN ('imperial', 'si') THEN lower(local_daily.units) ELSE 'metric' END
+            WHERE day >= today AND day < today + $4::INT
+            ORDER BY day, forecast_issued_at DESC
+        ),
+        latest AS (
+            SELECT DISTINCT ON (forecast_time)
+                forecast_time,
+                (temperature - celsius_offset) * celsius_scale AS temperature,
+                precipitation_probability,
+                rain_intensity * millimeters AS rain_intensity,
+                snow_intensity * millimeters AS snow_intensity,
+                cloud_cover
+            FROM weather_info
+            JOIN unit_systems ON unit_systems.units = CASE WHEN lower(weather_info.units) IN ('imperial', 'si') THEN lower(weather_info.units) ELSE 'metric' END
+            WHERE user_id = $1 AND location_id = $2
+            ORDER BY forecast_time, forecast_issued_at DESC
+        ),
+        hourly AS (
+            SELECT (latest.forecast_time AT TIME ZONE 'UTC' AT TIME ZONE zone.name)::DATE AS day,
+                MIN(temperature) AS temperature_min,
+                MAX(temperature) AS temperature_max,
+                MAX(precipitation_probability) AS precipitation_probability,
+                MAX(rain_intensity) AS rain_intensity,
+                MAX(snow_intensity) AS snow_intensity,
+                AVG(cloud_cover) AS cloud_cover
+            FROM latest, zone
+            GROUP BY 1
+        )
+        SELECT daily.day AS "day!",
+            daily.temperature_min,
+            daily.temperature_max,
+            daily.precipitation_accumulation,
+            hourly.precipitation_probability,
+            hourly.rain_intensity,
+            hourly.snow_intensity,
+            hourly.cloud_cover
+        FROM daily
+        LEFT JOIN hourly ON hourly.day = daily.day
+        UNION ALL
+        SELECT hourly.day,
+            hourly.temperature_min,
+            hourly.temperature_max,
+            NULL,
+            hourly.precipitation_probability,
+            hourly.rain_intensity,
+            hourly.snow_intensity,
+            hourly.cloud_cover
+        FROM hourly, zone
+        WHERE hourly.day >= zone.today AND hourly.day < zone.today + $4::INT
+            AND NOT EXISTS (SELECT 1 FROM daily)
+        ORDER BY 1
+        "#,
+        user_id,
+        location.id,
+        timezone,
+        days,
+        location.latitude,
+        location.longitude,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(summaries)
+}
@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::store::StoredForecast;
+
+/// Unit system of forecast values in responses and exports, and of stored
+/// rows, which record theirs in a `units` column; new rows are stored in
+/// `weather_client::STORED_UNITS`.
+///
+/// | variable                 | metric | imperial | si   |
+/// |--------------------------|--------|----------|------|
+/// | temperatures, dew point  | °C     | °F       | K    |
+/// | wind speed and gust      | m/s    | mph      | m/s  |
+/// | precipitation intensity  | mm/hr  | in/hr    | mm/hr|
+/// | pressure                 | hPa    | inHg     | Pa   |
+/// | visibility, cloud height | km     | mi       | m    |
+///
+/// Percentages, the UV index, wind direction and weather codes are the
+/// same in every system. Wind speed can be given in another unit with
+/// `WindSpeedUnit`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+    Si,
+}
+
+const MILLIMETERS_PER_INCH: f64 = 25.4;
+const HECTOPASCALS_PER_INCH_OF_MERCURY: f64 = 33.863_886_666_7;
+const KILOMETERS_PER_MILE: f64 = 1.609_344;
+const METERS_PER_SECOND_PER_MPH: f64 = 0.447_04;
+const KELVIN_AT_ZERO_CELSIUS: f64 = 273.15;
+const KILOMETERS_PER_HOUR_PER_METER_PER_SECOND: f64 = 3.6;
+/// Lowest wind speed in m/s of Beaufort forces 1 to 12.
+const BEAUFORT_SCALE: [f64; 12] = [
+    0.5, 1.6, 3.4, 5.5, 8.0, 10.8, 13.9, 17.2, 20.8, 24.5, 28.5, 32.7,
+];
+
+impl UnitSystem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "metric",
+            UnitSystem::Imperial => "imperial",
+            UnitSystem::Si => "si",
+        }
+    }
+
+    /// Converts a temperature from °C.
+    pub fn temperature(&self, celsius: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => celsius,
+            UnitSystem::Imperial => celsius * 9.0 / 5.0 + 32.0,
+            UnitSystem::Si => celsius + KELVIN_AT_ZERO_CELSIUS,
+        }
+    }
+
+    /// The wind speed unit of the system.
+    pub fn wind_speed_unit(&self) -> WindSpeedUnit {
+        match self {
+            UnitSystem::Metric | UnitSystem::Si => WindSpeedUnit::Ms,
+            UnitSystem::Imperial => WindSpeedUnit::Mph,
+        }
+    }
+
+    /// Converts a precipitation intensity from mm/hr, or an accumulation from
+    /// mm.
+    pub fn precipitation(&self, millimeters_per_hour: f64) -> f64 {
+        match self {
+            UnitSystem::Metric | UnitSystem::Si => millimeters_per_hour,
+            UnitSystem::Imperial => millimeters_per_hour / MILLIMETERS_PER_INCH,
+        }
+    }
+
+    /// Unit of accumulated precipitation.
+    pub fn accumulation_label(&self) -> &'static str {
+        match self {
+            UnitSystem::Metric | UnitSystem::Si => "mm",
+            UnitSystem::Imperial => "in",
+        }
+    }
+
+    /// Converts a pressure from hPa.
+    pub fn pressure(&self, hectopascals: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => hectopascals,
+            UnitSystem::Imperial => hectopascals / HECTOPASCALS_PER_INCH_OF_MERCURY,
+            UnitSystem::Si => hectopascals * 100.0,
+        }
+    }
+
+    /// Converts a visibility or cloud height from km.
+    pub fn distance(&self, kilometers: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => kilometers,
+            UnitSystem::Imperial => kilometers / KILOMETERS_PER_MILE,
+            UnitSystem::Si => kilometers * 1000.0,
+        }
+    }
+
+    /// Converts a temperature of this system to °C.
+    pub fn celsius(&self, temperature: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => temperature,
+            UnitSystem::Imperial => (temperature - 32.0) * 5.0 / 9.0,
+            UnitSystem::Si => temperature - KELVIN_AT_ZERO_CELSIUS,
+        }
+    }
+
+    /// Converts a wind speed of this system to m/s.
+    pub fn meters_per_second(&self, speed: f64) -> f64 {
+        match self {
+            UnitSystem::Metric | UnitSystem::Si => speed,
+            UnitSystem::Imperial => speed * METERS_PER_SECOND_PER_MPH,
+        }
+    }
+
+    /// Converts a precipitation intensity of this system to mm/hr.
+    pub fn millimeters_per_hour(&self, intensity: f64) -> f64 {
+        match self {
+            UnitSystem::Metric | UnitSystem::Si => intensity,
+            UnitSystem::Imperial => intensity * MILLIMETERS_PER_INCH,
+        }
+    }
+
+    /// Converts a pressure of this system to hPa.
+    pub fn hectopascals(&self, pressure: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => pressure,
+            UnitSystem::Imperial => pressure * HECTOPASCALS_PER_INCH_OF_MERCURY,
+            UnitSystem::Si => pressure / 100.0,
+        }
+    }
+
+    /// Converts a visibility or cloud height of this system to km.
+    pub fn kilometers(&self, distance: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => distance,
+            UnitSystem::Imperial => distance * KILOMETERS_PER_MILE,
+            UnitSystem::Si => distance / 1000.0,
+        }
+    }
+}
+
+impl TryFrom<String> for UnitSystem {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "metric" => Ok(Self::Metric),
+            "imperial" => Ok(Self::Imperial),
+            "si" => Ok(Self::Si),
+            other => Err(format!(
+                "{} is not a supported unit system. Use either `metric`, `imperial` or `si`.",
+                other
+            )),
+        }
+    }
+}
+
+/// Wind speed unit, named like Open-Meteo's `wind_speed_unit`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WindSpeedUnit {
+    Ms,
+    Kmh,
+    Mph,
+    /// The Beaufort force from 0 to 12.
+    Beaufort,
+}
+
+impl WindSpeedUnit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WindSpeedUnit::Ms => "ms",
+            WindSpeedUnit::Kmh => "kmh",
+            WindSpeedUnit::Mph => "mph",
+            WindSpeedUnit::Beaufort => "beaufort",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WindSpeedUnit::Ms => "m/s",
+            WindSpeedUnit::Kmh => "km/h",
+            WindSpeedUnit::Mph => "mph",
+            WindSpeedUnit::Beaufort => "Bft",
+        }
+    }
+
+    /// Converts a wind speed from m/s.
+    pub fn convert(&self, meters_per_second: f64) -> f64 {
+        match self {
+            WindSpeedUnit::Ms => meters_per_second,
+            WindSpeedUnit::Kmh => meters_per_second * KILOMETERS_PER_HOUR_PER_METER_PER_SECOND,
+            WindSpeedUnit::Mph => meters_per_second / METERS_PER_SECOND_PER_MPH,
+            WindSpeedUnit::Beaufort => BEAUFORT_SCALE
+                .iter()
+                .filter(|lowest| meters_per_second >= **lowest)
+                .count() as f64,
+        }
+    }
+}
+
+impl TryFrom<String> for WindSpeedUnit {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "ms" => Ok(Self::Ms),
+            "kmh" => Ok(Self::Kmh),
+            "mph" => Ok(Self::Mph),
+            "beaufort" => Ok(Self::Beaufort),
+            other => Err(format!(
+                "{} is not a supported wind speed unit. Use either `ms`, `kmh`, `mph` or `beaufort`.",
+                other
+            )),
+        }
+    }
+}
+
+impl Default for WindSpeedUnit {
+    fn default() -> Self {
+        UnitSystem::default().wind_speed_unit()
+    }
+}
+
+/// Units of every value of a response: a unit system, with the wind speed
+/// optionally in another unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Units {
+    pub system: UnitSystem,
+    pub wind_speed: WindSpeedUnit,
+}
+
+/// The unit of each converted variable, returned along with the values.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct UnitLabels {
+    pub system: &'static str,
+    pub temperature: &'static str,
+    pub wind_speed: &'static str,
+    pub precipitation: &'static str,
+    pub pressure: &'static str,
+    pub distance: &'static str,
+}
+
+impl Units {
+    /// The wind speed is in the unit of `system` unless `wind_speed` is given.
+    pub fn new(system: UnitSystem, wind_speed: Option<WindSpeedUnit>) -> Self {
+        Units {
+            system,
+            wind_speed: wind_speed.unwrap_or(system.wind_speed_unit()),
+        }
+    }
+
+    pub fn labels(&self) -> UnitLabels {
+        let (temperature, precipitation, pressure, distance) = match self.system {
+            UnitSystem::Metric => ("°C", "mm/hr", "hPa", "km"),
+            UnitSystem::Imperial => ("°F", "in/hr", "inHg", "mi"),
+            UnitSystem::Si => ("K", "mm/hr", "Pa", "m"),
+        };
+        UnitLabels {
+            system: self.system.as_str(),
+            temperature,
+            wind_speed: self.wind_speed.label(),
+            precipitation,
+            pressure,
+            distance,
+        }
+    }
+
+    /// Converts the values of `forecast` in place from the unit system its
+    /// row records, metric when the record is unknown, to these units.
+    pub fn convert(&self, forecast: &mut StoredForecast) {
+        let system = self.system;
+        let stored = UnitSystem::try_from(forecast.units.clone()).unwrap_or_else(|e| {
+            warn!(forecast_id = %forecast.id, "Assuming metric units, details: {}", e);
+            UnitSystem::Metric
+        });
+        for value in [
+            &mut forecast.temperature,
+            &mut forecast.temperature_apparent,
+            &mut forecast.dew_point,
+        ] {
+            *value = value.map(|temperature| system.temperature(stored.celsius(temperature)));
+        }
+        for value in [&mut forecast.wind_speed, &mut forecast.wind_gust] {
+            *value = value.map(|speed| self.wind_speed.convert(stored.meters_per_second(speed)));
+        }
+        for value in [
+            &mut forecast.rain_intensity,
+            &mut forecast.freezing_rain_intensity,
+            &mut forecast.sleet_intensity,
+            &mut forecast.snow_intensity,
+        ] {
+            *value =
+                value.map(|intensity| system.precipitation(stored.millimeters_per_hour(intensity)));
+        }
+        forecast.pressure_surface_level = forecast
+            .pressure_surface_level
+            .map(|pressure| system.pressure(stored.hectopascals(pressure)));
+        for value in [
+            &mut forecast.visibility,
+            &mut forecast.cloud_base,
+            &mut forecast.cloud_ceiling,
+        ] {
+            *value = value.map(|distance| system.distance(stored.kilometers(distance)));
+        }
+        forecast.units = system.as_str().to_string();
+    }
+}
@@ -0,0 +1,235 @@
+use axum::{
+    extract::{rejection::JsonRejection, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::json;
+use thiserror::Error;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    authentication::ApiUser,
+    errors::DbError,
+    geocoding::{geocode, reverse_geocode, GeocodeError},
+    start_up::AppState,
+    weather_client::{Coordinate, CoordinateParseError, Datum},
+};
+
+use super::store::{
+    find_or_create_location, find_or_create_place_location, get_subscribed_location,
+    is_location_creator, list_subscribed_locations, subscribe_location, unsubscribe_location,
+    update_location, Location, LocationChanges,
+};
+
+#[derive(Error, Debug)]
+pub enum LocationError {
+    #[error("Invalid JSON format: {0}")]
+    JsonError(#[from] JsonRejection),
+    #[error("Validation error: {0}")]
+    ValidationError(String),
+    #[error("Invalid Location format: {0}")]
+    CoordinateError(#[from] CoordinateParseError),
+    #[error("Location not found")]
+    NotFound,
+    #[error("Only the user who created the location can edit it")]
+    Forbidden,
+    #[error(transparent)]
+    GeocodeError(#[from] GeocodeError),
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] DbError),
+}
+
+impl IntoResponse for LocationError {
+    fn into_response(self) -> Response {
+        let candidates = match &self {
+            LocationError::GeocodeError(GeocodeError::Ambiguous { candidates, .. }) => {
+                json!(candidates)
+            }
+            _ => json!([]),
+        };
+        let (status_code, status) = match &self {
+            LocationError::JsonError(_) => (StatusCode::BAD_REQUEST, "JSON_ERROR"),
+            LocationError::ValidationError(_) | LocationError::CoordinateError(_) => {
+                (StatusCode::BAD_REQUEST, "VALIDATION_ERROR")
+            }
+            LocationError::NotFound => (StatusCode::NOT_FOUND, "NOT_FOUND"),
+            LocationError::Forbidden => (StatusCode::FORBIDDEN, "FORBIDDEN"),
+            LocationError::GeocodeError(GeocodeError::NotFound(_)) => {
+                (StatusCode::BAD_REQUEST, "GEOCODE_NOT_FOUND")
+            }
+            LocationError::GeocodeError(GeocodeError::Ambiguous { .. }) => {
+                (StatusCode::BAD_REQUEST, "GEOCODE_AMBIGUOUS")
+            }
+            LocationError::DatabaseError(e)
+            | LocationError::GeocodeError(GeocodeError::DatabaseError(e)) => {
+                error!("Location request failed, details: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "DATABASE_ERROR")
+            }
+        };
+        let body = Json(json!({
+            "status": status,
+            "content": self.to_string(),
+            "candidates": candidates,
+        }));
+        (status_code, body).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewLocation {
+    name: String,
+    /// A coordinate in any form accepted by `/update_weather`. When missing,
+    /// `name` is geocoded with the gazetteer.
+    location: Option<String>,
+    /// Coordinate system of `location`; WGS-84 by default.
+    datum: Option<Datum>,
+    timezone: Option<String>,
+    country: Option<String>,
+    aliases: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct LocationUpdate {
+    name: Option<String>,
+    timezone: Option<String>,
+    country: Option<String>,
+    aliases: Option<Vec<String>>,
+}
+
+impl LocationUpdate {
+    fn validate(self) -> Result<LocationChanges, LocationError> {
+        if let Some(name) = &self.name {
+            validate_name(name)?;
+        }
+        if let Some(timezone) = &self.timezone {
+            timezone.parse::<Tz>().map_err(|_| {
+                LocationError::ValidationError(format!("Unknown timezone {}", timezone))
+            })?;
+        }
+        Ok(LocationChanges {
+            name: self.name.map(|name| name.trim().to_string()),
+            timezone: self.timezone,
+            country: self.country,
+            aliases: self.aliases,
+        })
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), LocationError> {
+    match name.trim().chars().count() {
+        0 => Err(LocationError::ValidationError(
+            "Location name is empty".to_string(),
+        )),
+        1..=100 => Ok(()),
+        _ => Err(LocationError::ValidationError(
+            "Location name is longer than 100 characters".to_string(),
+        )),
+    }
+}
+
+#[tracing::instrument(name = "List locations", skip(state, user))]
+pub async fn list_locations(
+    State(state): State<AppState>,
+    user: ApiUser,
+) -> Result<Json<Vec<Location>>, LocationError> {
+    let locations = list_subscribed_locations(&user.user_id, &state.connect_pool).await?;
+    Ok(Json(locations))
+}
+
+/// Subscribes the user to the location at the given coordinate, creating it
+/// first when no user has stored it yet.
+#[tracing::instrument(name = "Create location", skip(state, user, request))]
+pub async fn create_location(
+    State(state): State<AppState>,
+    user: ApiUser,
+    request: Result<Json<NewLocation>, JsonRejection>,
+) -> Result<(StatusCode, Json<Location>), LocationError> {
+    let Json(request) = request?;
+    validate_name(&request.name)?;
+    let mut coordinate = request.location.map(Coordinate::parse).transpose()?;
+    if let (Some(coordinate), Some(datum)) = (coordinate.as_mut(), request.datum) {
+        coordinate.datum = datum;
+    }
+    let changes = LocationUpdate {
+        name: None,
+        timezone: request.timezone,
+        country: request.country,
+        aliases: request.aliases,
+    }
+    .validate()?;
+    let pool = &state.connect_pool;
+    let name = request.name.trim();
+    let location = match coordinate {
+        Some(coordinate) => match reverse_geocode(&coordinate, pool).await? {
+            Some(place) => {
+                find_or_create_place_location(&coordinate, &place, name, &user.user_id, pool)
+                    .await?
+            }
+            None => find_or_create_location(&coordinate, name, &user.user_id, pool).await?,
+        },
+        None => {
+            let place = geocode(name, pool).await?;
+            find_or_create_place_location(&place.coordinate(), &place, name, &user.user_id, pool)
+                .await?
+        }
+    };
+    subscribe_location(&user.user_id, &location.id, pool).await?;
+    // A location another user created keeps their name, aliases and timezone.
+    let location = match is_location_creator(&user.user_id, &location.id, pool).await? {
+        true => update_location(&location.id, changes, pool).await?,
+        false => location,
+    };
+    Ok((StatusCode::CREATED, Json(location)))
+}
+
+#[tracing::instrument(name = "Get location", skip(state, user))]
+pub async fn get_location(
+    State(state): State<AppState>,
+    user: ApiUser,
+    Path(location_id): Path<Uuid>,
+) -> Result<Json<Location>, LocationError> {
+    get_subscribed_location(&user.user_id, &location_id, &state.connect_pool)
+        .await?
+        .map(Json)
+        .ok_or(LocationError::NotFound)
+}
+
+/// Renames or annotates a location. Every subscriber sees the change, so
+/// only the user who created the location may make it. Its coordinate is
+/// fixed, since stored forecasts refer to it.
+#[tracing::instrument(name = "Update location", skip(state, user, request))]
+pub async fn edit_location(
+    State(state): State<AppState>,
+    user: ApiUser,
+    Path(location_id): Path<Uuid>,
+    request: Result<Json<LocationUpdate>, JsonRejection>,
+) -> Result<Json<Location>, LocationError> {
+    let Json(request) = request?;
+    let changes = request.validate()?;
+    let pool = &state.connect_pool;
+    get_subscribed_location(&user.user_id, &location_id, pool)
+        .await?
+        .ok_or(LocationError::NotFound)?;
+    if !is_location_creator(&user.user_id, &location_id, pool).await? {
+        return Err(LocationError::Forbidden);
+    }
+    let location = update_location(&location_id, changes, pool).await?;
+    Ok(Json(location))
+}
+
+/// Unsubscribes the user. The location and its forecasts are kept.
+#[tracing::instrument(name = "Delete location", skip(state, user))]
+pub async fn delete_location(
+    State(state): State<AppState>,
+    user: ApiUser,
+    Path(location_id): Path<Uuid>,
+) -> Result<StatusCode, LocationError> {
+    match unsubscribe_location(&user.user_id, &location_id, &state.connect_pool).await? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(LocationError::NotFound),
+    }
+}
@@ -0,0 +1,31 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    authentication::ApiUser,
+    geocoding::{search_places, Place},
+    start_up::AppState,
+};
+
+use super::crud::LocationError;
+
+#[derive(Deserialize, Debug)]
+pub struct GeocodeQuery {
+    /// A city or district name, in Chinese, pinyin or English, optionally
+    /// followed by a country code like "Suzhou, CN".
+    q: String,
+}
+
+/// Lists the gazetteer places matching a name so a client can pick one.
+#[tracing::instrument(name = "Geocode places", skip(state, _user))]
+pub async fn geocode_places(
+    State(state): State<AppState>,
+    _user: ApiUser,
+    Query(query): Query<GeocodeQuery>,
+) -> Result<Json<Vec<Place>>, LocationError> {
+    let places = search_places(&query.q, &state.connect_pool).await?;
+    Ok(Json(places))
+}
@@ -0,0 +1,13 @@
+mod crud;
+mod geocode;
+mod store;
+
+pub use crud::{
+    create_location, delete_location, edit_location, get_location, list_locations, LocationError,
+};
+pub use geocode::geocode_places;
+pub use store::{
+    find_or_create_location, find_or_create_place_location, get_subscribed_location,
+    list_subscribed_locations, longitude_ranges, nearest_subscribed_location, subscribe_location,
+    Location,
+};
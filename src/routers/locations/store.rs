@@ -0,0 +1,326 @@
+use chrono_tz::Tz;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    errors::DbError,
+    geocoding::{resolve_timezone, timezone_at, Place},
+    weather_client::{Coordinate, Datum},
+};
+
+/// Splits `longitude ± delta` into two ranges within [-180, 180] when it
+/// crosses the antimeridian; otherwise both ranges are the same.
+pub fn longitude_ranges(longitude: f64, delta: f64) -> [(f64, f64); 2] {
+    let (west, east) = (longitude - delta, longitude + delta);
+    if delta >= 180.0 {
+        [(-180.0, 180.0); 2]
+    } else if west < -180.0 {
+        [(west + 360.0, 180.0), (-180.0, east)]
+    } else if east > 180.0 {
+        [(west, 180.0), (-180.0, east - 360.0)]
+    } else {
+        [(west, east); 2]
+    }
+}
+
+/// A named place forecasts are stored for, shared by every subscribed user.
+#[derive(Serialize, Debug)]
+pub struct Location {
+    pub id: Uuid,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub timezone: Option<String>,
+    pub country: Option<String>,
+    /// First-level administrative division, e.g. a province.
+    pub admin_region: Option<String>,
+    pub aliases: Vec<String>,
+    /// Datum the coordinate was submitted in; it is stored as WGS-84.
+    pub source_datum: String,
+}
+
+impl Location {
+    pub fn coordinate(&self) -> Coordinate {
+        Coordinate {
+            latitude: self.latitude,
+            longitude: self.longitude,
+            datum: Datum::Wgs84,
+        }
+    }
+
+    /// The timezone local times and days of this location are rendered in.
+    pub fn tz(&self) -> Tz {
+        resolve_timezone(self.timezone.as_deref(), &self.coordinate())
+    }
+}
+
+/// Returns the location stored at `coordinate`, converted to WGS-84 and
+/// snapped to the stored precision, creating it with `name` and
+/// `created_by`, the only user allowed to edit it, when there is none. A new
+/// location records the datum it was submitted in and the timezone at the
+/// coordinate; an existing location keeps its name, creator and datum and
+/// gets a timezone when it has none.
+#[tracing::instrument(name = "Find or create location", skip(coordinate, name, pool))]
+pub async fn find_or_create_location(
+    coordinate: &Coordinate,
+    name: &str,
+    created_by: &Uuid,
+    pool: &PgPool,
+) -> Result<Location, DbError> {
+    let wgs84 = coordinate.to_wgs84().snapped();
+    let timezone = timezone_at(&wgs84).map(|timezone| timezone.name());
+    let location = sqlx::query_as!(
+        Location,
+        r#"
+        INSERT INTO locations (id, name, latitude, longitude, source_datum, created_by, timezone)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (latitude, longitude) DO UPDATE
+                SET timezone = COALESCE(locations.timezone, EXCLUDED.timezone)
+            RETURNING id, name, latitude, longitude, timezone, country, admin_region, aliases, source_datum
+        "#,
+        Uuid::new_v4(),
+        name,
+        wgs84.latitude,
+        wgs84.longitude,
+        coordinate.datum.as_str(),
+        created_by,
+        timezone,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(location)
+}
+
+/// Like `find_or_create_location` for a coordinate at or near a gazetteer
+/// place, also filling in the country, administrative region and timezone
+/// from the place where the location has none.
+#[tracing::instrument(
+    name = "Find or create place location",
+    skip(coordinate, place, name, pool)
+)]
+pub async fn find_or_create_place_location(
+    coordinate: &Coordinate,
+    place: &Place,
+    name: &str,
+    created_by: &Uuid,
+    pool: &PgPool,
+) -> Result<Location, DbError> {
+    let location = find_or_create_location(coordinate, name, created_by, pool).await?;
+    let location = sqlx::query_as!(
+        Location,
+        r#"
+        UPDATE locations
+        SET
+            country = COALESCE(country, $2),
+            timezone = COALESCE(timezone, $3),
+            admin_region = COALESCE(admin_region, $4)
+        WHERE id = $1
+        RETURNING id, name, latitude, longitude, timezone, country, admin_region, aliases, source_datum
+        "#,
+        location.id,
+        place.country_code,
+        place.timezone,
+        place.admin1_name,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(location)
+}
+
+#[tracing::instrument(name = "Subscribe location", skip(pool))]
+pub async fn subscribe_location(
+    user_id: &Uuid,
+    location_id: &Uuid,
+    pool: &PgPool,
+) -> Result<(), DbError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_location_subscriptions (user_id, location_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        location_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Returns `false` when the user was not subscribed to the location.
+#[tracing::instrument(name = "Unsubscribe location", skip(pool))]
+pub async fn unsubscribe_location(
+    user_id: &Uuid,
+    location_id: &Uuid,
+    pool: &PgPool,
+) -> Result<bool, DbError> {
+    let result = sqlx::query!(
+        "DELETE FROM user_location_subscriptions WHERE user_id = $1 AND location_id = $2",
+        user_id,
+        location_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(name = "List subscribed locations", skip(pool))]
+pub async fn list_subscribed_locations(
+    user_id: &Uuid,
+    pool: &PgPool,
+) -> Result<Vec<Location>, DbError> {
+    let locations = sqlx::query_as!(
+        Location,
+        r#"
+        SELECT l.id, l.name, l.latitude, l.longitude, l.timezone, l.country, l.admin_region, l.aliases, l.source_datum
+        FROM locations l
+        JOIN user_location_subscriptions s ON s.location_id = l.id
+        WHERE s.user_id = $1
+        ORDER BY s.created_at, l.name
+        "#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(locations)
+}
+
+/// Only locations the user is subscribed to are visible to them.
+#[tracing::instrument(name = "Get subscribed location", skip(pool))]
+pub async fn get_subscribed_location(
+    user_id: &Uuid,
+    location_id: &Uuid,
+    pool: &PgPool,
+) -> Result<Option<Location>, DbError> {
+    let location = sqlx::query_as!(
+        Location,
+        r#"
+        SELECT l.id, l.name, l.latitude, l.longitude, l.timezone, l.country, l.admin_region, l.aliases, l.source_datum
+        FROM locations l
+        JOIN user_location_subscriptions s ON s.location_id = l.id
+        WHERE s.user_id = $1 AND l.id = $2
+        "#,
+        user_id,
+        location_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(location)
+}
+
+/// Locations are shared by every subscriber, so only their creator may
+/// change them.
+#[tracing::instrument(name = "Check location creator", skip(pool))]
+pub async fn is_location_creator(
+    user_id: &Uuid,
+    location_id: &Uuid,
+    pool: &PgPool,
+) -> Result<bool, DbError> {
+    let created_by = sqlx::query_scalar!(
+        "SELECT created_by FROM locations WHERE id = $1",
+        location_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .flatten();
+    Ok(created_by.as_ref() == Some(user_id))
+}
+
+const KM_PER_DEGREE: f64 = 111.32;
+
+/// Returns the subscribed location nearest to `coordinate` within
+/// `radius_km`, with its great-circle distance in kilometers. Candidates are
+/// narrowed by a bounding box first, wrapped at the antimeridian.
+#[tracing::instrument(name = "Find nearest subscribed location", skip(coordinate, pool))]
+pub async fn nearest_subscribed_location(
+    user_id: &Uuid,
+    coordinate: &Coordinate,
+    radius_km: f64,
+    pool: &PgPool,
+) -> Result<Option<(Location, f64)>, DbError> {
+    let coordinate = coordinate.to_wgs84();
+    let latitude_delta = radius_km / KM_PER_DEGREE;
+    let longitude_delta = latitude_delta / coordinate.latitude.to_radians().cos().max(0.01);
+    let [(west, east), (wrapped_west, wrapped_east)] =
+        longitude_ranges(coordinate.longitude, longitude_delta);
+    let row = sqlx::query!(
+        r#"
+        SELECT l.id, l.name, l.latitude, l.longitude, l.timezone, l.country, l.admin_region, l.aliases, l.source_datum,
+            2 * 6371 * ASIN(SQRT(
+                POWER(SIN(RADIANS(l.latitude - $2) / 2), 2)
+                + COS(RADIANS($2)) * COS(RADIANS(l.latitude)) * POWER(SIN(RADIANS(l.longitude - $3) / 2), 2)
+            )) AS "distance_km!"
+        FROM locations l
+        JOIN user_location_subscriptions s ON s.location_id = l.id
+        WHERE s.user_id = $1
+            AND l.latitude BETWEEN $2::FLOAT - $4::FLOAT AND $2 + $4
+            AND (l.longitude BETWEEN $5 AND $6 OR l.longitude BETWEEN $7 AND $8)
+        ORDER BY "distance_km!"
+        LIMIT 1
+        "#,
+        user_id,
+        coordinate.latitude,
+        coordinate.longitude,
+        latitude_delta,
+        west,
+        east,
+        wrapped_west,
+        wrapped_east,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.filter(|row| row.distance_km <= radius_km).map(|row| {
+        let location = Location {
+            id: row.id,
+            name: row.name,
+            latitude: row.latitude,
+            longitude: row.longitude,
+            timezone: row.timezone,
+            country: row.country,
+            admin_region: row.admin_region,
+            aliases: row.aliases,
+            source_datum: row.source_datum,
+        };
+        (location, row.distance_km)
+    }))
+}
+
+/// Fields left as `None` keep their current value.
+#[derive(Default)]
+pub struct LocationChanges {
+    pub name: Option<String>,
+    pub timezone: Option<String>,
+    pub country: Option<String>,
+    pub aliases: Option<Vec<String>>,
+}
+
+#[tracing::instrument(name = "Update location", skip(changes, pool))]
+pub async fn update_location(
+    location_id: &Uuid,
+    changes: LocationChanges,
+    pool: &PgPool,
+) -> Result<Location, DbError> {
+    let location = sqlx::query_as!(
+        Location,
+        r#"
+        UPDATE locations
+        SET
+            name = COALESCE($2, name),
+            timezone = COALESCE($3, timezone),
+            country = COALESCE($4, country),
+            aliases = COALESCE($5, aliases),
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        RETURNING id, name, latitude, longitude, timezone, country, admin_region, aliases, source_datum
+        "#,
+        location_id,
+        changes.name,
+        changes.timezone,
+        changes.country,
+        changes.aliases.as_deref(),
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(location)
+}
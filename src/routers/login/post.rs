@@ -176,7 +176,7 @@ pub async fn login(
                         "User logging failed, database error, details: {}",
                         err.to_string()
                     );
-                    LoginError::DatabaseError(err.into())
+                    LoginError::DatabaseError(err)
                 }
             };
             Err(login_redirect(e, messages))
@@ -1,9 +1,13 @@
 mod admin;
+mod forecasts;
 mod home;
+mod locations;
 mod login;
 mod weather;
 
 pub use admin::*;
+pub use forecasts::*;
 pub use home::*;
+pub use locations::*;
 pub use login::*;
 pub use weather::*;
@@ -0,0 +1,128 @@
+use std::io::{Read, Write};
+
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use sqlx::PgPool;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::weather_client::{Coordinate, ProviderForecast, WeatherProviderKind};
+
+use super::storage::ForecastParseError;
+
+fn compress_payload(body: &[u8]) -> Result<Vec<u8>, ForecastParseError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body)?;
+    Ok(encoder.finish()?)
+}
+
+pub fn decompress_payload(payload: &[u8]) -> Result<Vec<u8>, ForecastParseError> {
+    let mut body = Vec::new();
+    GzDecoder::new(payload).read_to_end(&mut body)?;
+    Ok(body)
+}
+
+/// Keeps the provider response body and status exactly as received, error
+/// responses included, so it can be re-parsed after a schema change or a
+/// parse bug.
+#[tracing::instrument(
+    name = "Archive raw forecast",
+    skip(forecast, location, city_name, pool)
+)]
+pub async fn archive_raw_forecast(
+    forecast: &ProviderForecast,
+    location: &Coordinate,
+    city_name: &str,
+    user_id: &Uuid,
+    pool: &PgPool,
+) -> Result<Uuid, ForecastParseError> {
+    let id = Uuid::new_v4();
+    let payload = compress_payload(&forecast.body)?;
+    sqlx::query!(
+        r#"
+        INSERT INTO forecast_raw
+            (id, user_id, provider, latitude, longitude, city_name, http_status, requested_at, payload)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+        id,
+        user_id,
+        forecast.provider.as_str(),
+        location.latitude,
+        location.longitude,
+        city_name,
+        forecast.http_status as i16,
+        forecast.requested_at,
+        payload,
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| ForecastParseError::DatabaseError(e.into()))?;
+
+    Ok(id)
+}
+
+/// A raw forecast read back from the archive, still compressed, so one that
+/// no longer decompresses can be skipped like one that no longer parses.
+pub struct ArchivedForecast {
+    pub provider: WeatherProviderKind,
+    pub http_status: u16,
+    pub requested_at: DateTime<Utc>,
+    payload: Vec<u8>,
+}
+
+impl ArchivedForecast {
+    pub fn decompress(self) -> Result<ProviderForecast, ForecastParseError> {
+        Ok(ProviderForecast {
+            provider: self.provider,
+            body: decompress_payload(&self.payload)?,
+            http_status: self.http_status,
+            requested_at: self.requested_at,
+        })
+    }
+}
+
+/// Returns the newest successful raw forecast archived for exactly
+/// `location` at or after `since`, from any user.
+#[tracing::instrument(name = "Find recent raw forecast", skip(location, pool))]
+pub async fn recent_raw_forecast(
+    location: &Coordinate,
+    since: DateTime<Utc>,
+    pool: &PgPool,
+) -> Result<Option<ArchivedForecast>, ForecastParseError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT provider, http_status, requested_at, payload
+        FROM forecast_raw
+        WHERE latitude = $1 AND longitude = $2
+            AND requested_at >= $3
+            AND http_status BETWEEN 200 AND 299
+        ORDER BY requested_at DESC
+        LIMIT 1
+        "#,
+        location.latitude,
+        location.longitude,
+        since,
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| ForecastParseError::DatabaseError(e.into()))?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    // An archive written by a provider that is no longer known is not
+    // usable; fetching anew is the safe fallback.
+    let provider = match WeatherProviderKind::try_from(row.provider) {
+        Ok(provider) => provider,
+        Err(e) => {
+            error!("Recent archived forecast skipped, details: {}", e);
+            return Ok(None);
+        }
+    };
+    Ok(Some(ArchivedForecast {
+        provider,
+        http_status: row.http_status as u16,
+        requested_at: row.requested_at,
+        payload: row.payload,
+    }))
+}
@@ -2,36 +2,63 @@ use axum::extract::rejection::JsonRejection;
 use axum::extract::State;
 use axum::response::IntoResponse;
 use axum::Json;
+use chrono::{Duration, Utc};
 use reqwest::StatusCode;
 use serde::Deserialize;
 use serde::Serialize;
 use sqlx::PgPool;
 use thiserror::Error;
-use tracing::error;
+use tracing::{error, warn};
 use uuid::Uuid;
 
 use crate::errors::DbError;
+use crate::geocoding::{geocode, reverse_geocode, GeocodeError};
+use crate::routers::{
+    find_or_create_location, find_or_create_place_location, get_subscribed_location,
+    subscribe_location,
+};
 use crate::start_up::AppState;
 use crate::weather_client::Coordinate;
 use crate::weather_client::CoordinateParseError;
+use crate::weather_client::Datum;
+use crate::weather_client::ProviderForecast;
+use crate::weather_client::WeatherClient;
 
-use super::storage::parse_forecast_data;
+use super::archive::{archive_raw_forecast, recent_raw_forecast};
 use super::storage::ForecastParseError;
+use super::storage::{forecast_version_stored, parse_forecast_data};
 
+/// Either `location_id` of a subscribed location, or a `location` coordinate
+/// that is stored as a location on first use, named after the nearest
+/// gazetteer place or else the optional `city_name`. A `city_name` alone is
+/// geocoded with the gazetteer. `datum` names the coordinate system of
+/// `location`, e.g. `gcj02` for WeChat location messages; WGS-84 by default.
+///
+/// A forecast archived for the same location within
+/// `forecasts.cache_ttl_seconds` is reused unless `force_refresh` is set.
 #[derive(Deserialize)]
 pub struct WeatherRequestInfo {
     token: String,
-    location: String,
-    city_name: String,
+    location: Option<String>,
+    city_name: Option<String>,
+    location_id: Option<Uuid>,
+    datum: Option<Datum>,
+    #[serde(default)]
+    force_refresh: bool,
 }
 
 #[derive(Serialize)]
 pub struct WeatherResponse {
     status: String,
     content: String,
+    /// Whether the stored forecast came from the cache rather than the
+    /// provider; only set on success.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cached: Option<bool>,
 }
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum UpdateWeatherError {
     #[error("Invalid JSON format: {0}")]
     UserPostJsonError(#[from] JsonRejection),
@@ -45,12 +72,17 @@ pub enum UpdateWeatherError {
     DatabaseError(#[from] DbError),
     #[error("Request weather server error: {0}")]
     WeatherServerError(#[from] reqwest::Error),
+    #[error("Weather server answered with status {0}")]
+    WeatherServerStatus(u16),
     #[error("Forecast parse error: {0}")]
     ForecastWriteError(#[from] ForecastParseError),
+    #[error(transparent)]
+    GeocodeError(#[from] GeocodeError),
 }
 
 impl IntoResponse for UpdateWeatherError {
     fn into_response(self) -> axum::response::Response {
+        let message = self.to_string();
         let (status_code, status, content) = match &self {
             UpdateWeatherError::UserPostJsonError(json_rejection) => {
                 let content_message = match json_rejection {
@@ -76,10 +108,15 @@ impl IntoResponse for UpdateWeatherError {
                     CoordinateParseError::Format => "Location format error",
                     CoordinateParseError::ParseFloat(_) => "Location parse number error",
                     CoordinateParseError::InvalidValue => "Location range error",
+                    CoordinateParseError::InvalidDms(_)
+                    | CoordinateParseError::InvalidGeoUri(_)
+                    | CoordinateParseError::InvalidPlusCode(_)
+                    | CoordinateParseError::InvalidGeohash(_) => message.as_str(),
                 };
                 (StatusCode::BAD_REQUEST, "JSON_ERROR", content_message)
             }
-            UpdateWeatherError::WeatherServerError(_) => (
+            UpdateWeatherError::WeatherServerError(_)
+            | UpdateWeatherError::WeatherServerStatus(_) => (
                 StatusCode::BAD_REQUEST,
                 "WEATHER_SERVER_ERROR",
                 "Weather server error",
@@ -100,6 +137,28 @@ impl IntoResponse for UpdateWeatherError {
                     "JSON_ERROR",
                     "Weather data json parse error",
                 ),
+                ForecastParseError::CompressionError(_) => (
+                    StatusCode::BAD_REQUEST,
+                    "WEATHER_ARCHIVE_ERROR",
+                    "Weather data archive error",
+                ),
+            },
+            UpdateWeatherError::GeocodeError(error) => match error {
+                GeocodeError::NotFound(_) => (
+                    StatusCode::BAD_REQUEST,
+                    "GEOCODE_NOT_FOUND",
+                    message.as_str(),
+                ),
+                GeocodeError::Ambiguous { .. } => (
+                    StatusCode::BAD_REQUEST,
+                    "GEOCODE_AMBIGUOUS",
+                    message.as_str(),
+                ),
+                GeocodeError::DatabaseError(_) => (
+                    StatusCode::BAD_REQUEST,
+                    "WEATHER_DATABASE_ERROR",
+                    "Database connection error",
+                ),
             },
             UpdateWeatherError::DatabaseError(_) => (
                 StatusCode::BAD_REQUEST,
@@ -111,6 +170,7 @@ impl IntoResponse for UpdateWeatherError {
         let body = Json(WeatherResponse {
             status: status.to_string(),
             content: content.to_string(),
+            cached: None,
         });
 
         (status_code, body).into_response()
@@ -135,43 +195,223 @@ pub async fn update_weather_data(
     let mut weather_response = WeatherResponse {
         status: "SUCCESS_UPDATE".to_owned(),
         content: "Success update weather info".to_owned(),
+        cached: None,
     };
     if !validate_bool {
         weather_response.content = "Permission error, please log in again".to_owned();
         weather_response.status = "FAILED_UPDATE".to_owned();
         return Ok(Json(weather_response));
     }
-    let location = Coordinate::parse(request.location)
-        .map_err(|err| UpdateWeatherError::LocationError(err))?;
-    let city_name = request.city_name;
-    let forecast_value = state
-        .weather_client
-        .get_weather_forecast(&location)
-        .await
-        .map_err(|err| {
-            error!(
-                "The JSON data sent by the user is incorrect, details: {}",
-                err.to_string()
-            );
-            UpdateWeatherError::WeatherServerError(err)
-        })?;
     let user_id = get_user_id_by_token(&state.connect_pool, &user_token).await?;
-    let _ = parse_forecast_data(
-        forecast_value,
-        &location,
-        city_name,
+    let location = match (request.location_id, request.location, request.city_name) {
+        (Some(location_id), _, _) => {
+            get_subscribed_location(&user_id, &location_id, &state.connect_pool)
+                .await?
+                .ok_or_else(|| {
+                    UpdateWeatherError::UserValidationError("Location not found".to_string())
+                })?
+        }
+        (None, Some(location), city_name) => {
+            let mut coordinate =
+                Coordinate::parse(location).map_err(UpdateWeatherError::LocationError)?;
+            if let Some(datum) = request.datum {
+                coordinate.datum = datum;
+            }
+            // A nearby gazetteer place names new locations consistently,
+            // whatever the client typed.
+            let location = match reverse_geocode(&coordinate, &state.connect_pool).await? {
+                Some(place) => {
+                    find_or_create_place_location(
+                        &coordinate,
+                        &place,
+                        place.display_name(),
+                        &user_id,
+                        &state.connect_pool,
+                    )
+                    .await?
+                }
+                None => {
+                    let city_name = city_name.unwrap_or_default();
+                    find_or_create_location(&coordinate, &city_name, &user_id, &state.connect_pool)
+                        .await?
+                }
+            };
+            subscribe_location(&user_id, &location.id, &state.connect_pool).await?;
+            location
+        }
+        (None, None, Some(city_name)) => {
+            let place = geocode(&city_name, &state.connect_pool).await?;
+            let location = find_or_create_place_location(
+                &place.coordinate(),
+                &place,
+                &city_name,
+                &user_id,
+                &state.connect_pool,
+            )
+            .await?;
+            subscribe_location(&user_id, &location.id, &state.connect_pool).await?;
+            location
+        }
+        (None, None, None) => {
+            return Err(UpdateWeatherError::UserValidationError(
+                "One of location, city_name or location_id is required".to_string(),
+            ))
+        }
+    };
+    let max_age = match request.force_refresh {
+        true => Duration::zero(),
+        false => Duration::seconds(state.forecast_settings.cache_ttl_seconds as i64),
+    };
+    let served = cached_or_refresh_forecast(
+        &state.weather_client,
+        &location.coordinate(),
+        location.name,
         &user_id,
+        max_age,
         &state.connect_pool,
     )
-    .await
-    .map_err(|err| {
+    .await?;
+    if served.cached {
+        weather_response.content = "Weather info is fresh, served from cache".to_owned();
+    }
+    weather_response.cached = Some(served.cached);
+    Ok(Json(weather_response))
+}
+
+/// A forecast stored for a user, and whether it came from the archive
+/// instead of a provider.
+pub struct ServedForecast {
+    pub forecast: ProviderForecast,
+    pub cached: bool,
+}
+
+/// Stores for `user_id` the forecast archived for `location` within
+/// `max_age`, by any user, or else fetches a fresh one. An archive the user
+/// already has stored is not parsed again, and one that no longer parses is
+/// skipped.
+#[tracing::instrument(
+    name = "Cached or refresh forecast",
+    skip(weather_client, location, city_name, pool)
+)]
+pub async fn cached_or_refresh_forecast(
+    weather_client: &WeatherClient,
+    location: &Coordinate,
+    city_name: String,
+    user_id: &Uuid,
+    max_age: Duration,
+    pool: &PgPool,
+) -> Result<ServedForecast, UpdateWeatherError> {
+    if max_age > Duration::zero() {
+        let cached = recent_raw_forecast(location, Utc::now() - max_age, pool)
+            .await
+            .map_err(|err| {
+                error!("Error reading archived forecast, details: {}", err);
+                UpdateWeatherError::ForecastWriteError(err)
+            })?;
+        if let Some(archived) = cached {
+            let stored =
+                forecast_version_stored(user_id, location, archived.requested_at, pool).await?;
+            // Parsing upserts under the original issue time, so reusing an
+            // archive never duplicates a forecast version.
+            let result = match archived.decompress() {
+                Ok(forecast) if stored => Ok(forecast),
+                Ok(forecast) => {
+                    parse_forecast_data(&forecast, location, city_name.clone(), user_id, pool)
+                        .await
+                        .map(|()| forecast)
+                }
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(forecast) => {
+                    return Ok(ServedForecast {
+                        forecast,
+                        cached: true,
+                    })
+                }
+                Err(err @ ForecastParseError::DatabaseError(_)) => {
+                    error!("Error storing cached forecast, details: {}", err);
+                    return Err(UpdateWeatherError::ForecastWriteError(err));
+                }
+                Err(err) => warn!(
+                    "Archived forecast is unusable, fetching a fresh one, details: {}",
+                    err
+                ),
+            }
+        }
+    }
+    let forecast = refresh_forecast(weather_client, location, city_name, user_id, pool).await?;
+    Ok(ServedForecast {
+        forecast,
+        cached: false,
+    })
+}
+
+/// Fetches a fresh forecast for `location`, archives every raw response, even
+/// an error one or one passed over for the next provider, and stores the
+/// parsed forecast for `user_id`. Returns the forecast stored, so it can be
+/// stored for other users of the location without fetching it again.
+#[tracing::instrument(
+    name = "Refresh forecast",
+    skip(weather_client, location, city_name, pool)
+)]
+async fn refresh_forecast(
+    weather_client: &WeatherClient,
+    location: &Coordinate,
+    city_name: String,
+    user_id: &Uuid,
+    pool: &PgPool,
+) -> Result<ProviderForecast, UpdateWeatherError> {
+    let attempts = weather_client.get_weather_forecast(location).await;
+    for response in &attempts.responses {
+        archive_raw_forecast(response, location, &city_name, user_id, pool)
+            .await
+            .map_err(|err| {
+                error!(
+                    "Error archiving raw weather forecast, details: {}",
+                    err.to_string()
+                );
+                UpdateWeatherError::ForecastWriteError(err)
+            })?;
+    }
+    let forecast_value = attempts.served().map_err(|err| {
         error!(
-            "Error parsing weather forecast data, details: {}",
+            "Failed to request weather forecast, details: {}",
             err.to_string()
         );
-        UpdateWeatherError::ForecastWriteError(err)
+        UpdateWeatherError::WeatherServerError(err)
     })?;
-    Ok(Json(weather_response))
+    if !forecast_value.is_success() {
+        error!(
+            status = forecast_value.http_status,
+            "Weather server answered with an error status"
+        );
+        return Err(UpdateWeatherError::WeatherServerStatus(
+            forecast_value.http_status,
+        ));
+    }
+    store_forecast(&forecast_value, location, city_name, user_id, pool).await?;
+    Ok(forecast_value)
+}
+
+/// Stores an already fetched forecast for `user_id`.
+#[tracing::instrument(name = "Store forecast", skip(forecast, location, city_name, pool))]
+pub async fn store_forecast(
+    forecast: &ProviderForecast,
+    location: &Coordinate,
+    city_name: String,
+    user_id: &Uuid,
+    pool: &PgPool,
+) -> Result<(), UpdateWeatherError> {
+    parse_forecast_data(forecast, location, city_name, user_id, pool)
+        .await
+        .map_err(|err| {
+            error!(
+                "Error parsing weather forecast data, details: {}",
+                err.to_string()
+            );
+            UpdateWeatherError::ForecastWriteError(err)
+        })
 }
 
 #[tracing::instrument(name = "Update weather validate token", skip(token, pool))]
@@ -0,0 +1,49 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::weather_client::Coordinate;
+
+/// Narrows a batch job to a time window and/or an exact stored coordinate.
+/// Reprocessing applies the window to request time, verification to forecast time.
+#[derive(Default)]
+pub struct ForecastFilter {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub location: Option<Coordinate>,
+}
+
+impl ForecastFilter {
+    /// Builds a filter from user input where an empty string means unbounded.
+    /// Times are RFC 3339 or `YYYY-MM-DDTHH:MM` in UTC, the location is `lat,lon`
+    /// and is snapped like stored coordinates.
+    pub fn parse(from: &str, to: &str, location: &str) -> Result<Self, String> {
+        let location = match location.trim() {
+            "" => None,
+            location => Some(
+                Coordinate::parse(location.to_string())
+                    .map_err(|e| format!("Invalid location {}: {}", location, e))?
+                    .to_wgs84()
+                    .snapped(),
+            ),
+        };
+        Ok(ForecastFilter {
+            from: parse_time(from)?,
+            to: parse_time(to)?,
+            location,
+        })
+    }
+}
+
+/// Parses RFC 3339 or `YYYY-MM-DDTHH:MM` in UTC, an empty string as `None`.
+pub fn parse_time(value: &str) -> Result<Option<DateTime<Utc>>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M").map(|time| time.and_utc())
+        })
+        .map(Some)
+        .map_err(|e| format!("Invalid time {}: {}", value, e))
+}
@@ -1,5 +1,20 @@
+mod archive;
 mod fetcher;
+mod filter;
+mod observation;
+mod reprocess;
 mod storage;
+mod verification;
 
-pub use fetcher::update_weather_data;
+pub use archive::decompress_payload;
+pub use fetcher::{
+    cached_or_refresh_forecast, store_forecast, update_weather_data, ServedForecast,
+    UpdateWeatherError,
+};
+pub use filter::{parse_time, ForecastFilter};
+pub use observation::{
+    record_observation, record_observations, ObservationError, ObservationSummary,
+};
+pub use reprocess::{reprocess_raw_forecasts, ReprocessSummary};
 pub use storage::ForecastParseError;
+pub use verification::{verify_forecasts, VerificationStat};
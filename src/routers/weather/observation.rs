@@ -0,0 +1,134 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    errors::DbError,
+    weather_client::{Coordinate, Datum, WeatherClient, STORED_UNITS},
+};
+
+/// Forecast locations issued within this window get an observation recorded.
+const ACTIVE_LOCATION_HOURS: i32 = 24;
+
+#[derive(Error, Debug)]
+pub enum ObservationError {
+    #[error("Weather provider request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Weather provider answered with status {0}")]
+    ProviderStatus(u16),
+    #[error("Json data parse Error: {0}")]
+    JsonParseError(#[from] serde_json::Error),
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] DbError),
+}
+
+#[derive(Debug, Default)]
+pub struct ObservationSummary {
+    pub recorded: usize,
+    pub failed: usize,
+}
+
+/// Fetches current conditions at `location` and stores them in `observations`.
+#[tracing::instrument(name = "Record observation", skip(client, location, pool))]
+pub async fn record_observation(
+    client: &WeatherClient,
+    location: &Coordinate,
+    city_name: Option<String>,
+    pool: &PgPool,
+) -> Result<(), ObservationError> {
+    let observation = client.get_observation(location).await?;
+    if !observation.is_success() {
+        return Err(ObservationError::ProviderStatus(observation.http_status));
+    }
+    let provider = observation.provider.as_str();
+    let values = observation.parse()?;
+    sqlx::query!(
+        r#"
+        INSERT INTO observations
+            (id, latitude, longitude, city_name, provider, observed_at,
+             rain_intensity, sleet_intensity, snow_intensity,
+             temperature, temperature_apparent, dew_point, humidity,
+             wind_speed, wind_direction, wind_gust,
+             pressure_surface_level, visibility, cloud_cover, weather_code, units)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21)
+            ON CONFLICT (latitude, longitude, provider, observed_at) DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        location.latitude,
+        location.longitude,
+        city_name,
+        provider,
+        values.time.naive_utc(),
+        values.rain_intensity,
+        values.sleet_intensity,
+        values.snow_intensity,
+        values.temperature,
+        values.temperature_apparent,
+        values.dew_point,
+        values.humidity,
+        values.wind_speed,
+        values.wind_direction,
+        values.wind_gust,
+        values.pressure_surface_level,
+        values.visibility,
+        values.cloud_cover,
+        values.weather_code,
+        STORED_UNITS,
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| ObservationError::DatabaseError(e.into()))?;
+
+    Ok(())
+}
+
+/// Records an observation for every location that had a forecast issued
+/// recently, so each forecast hour can later be verified. A location whose
+/// provider request fails is logged and counted instead of aborting the run.
+#[tracing::instrument(name = "Record observations", skip(client, pool))]
+pub async fn record_observations(
+    client: &WeatherClient,
+    pool: &PgPool,
+) -> Result<ObservationSummary, ObservationError> {
+    let locations = sqlx::query!(
+        r#"
+        SELECT latitude, longitude, MAX(city_name) AS city_name
+        FROM weather_info
+        WHERE forecast_issued_at >= NOW() - make_interval(hours => $1)
+        GROUP BY latitude, longitude
+        "#,
+        ACTIVE_LOCATION_HOURS,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ObservationError::DatabaseError(e.into()))?;
+
+    let mut summary = ObservationSummary::default();
+    for row in locations {
+        let location = Coordinate {
+            latitude: row.latitude,
+            longitude: row.longitude,
+            datum: Datum::Wgs84,
+        };
+        match record_observation(client, &location, row.city_name, pool).await {
+            Ok(()) => summary.recorded += 1,
+            Err(ObservationError::DatabaseError(e)) => {
+                return Err(ObservationError::DatabaseError(e))
+            }
+            Err(e) => {
+                error!(
+                    location = format!("{},{}", row.latitude, row.longitude),
+                    "Failed to record observation, details: {}", e
+                );
+                summary.failed += 1;
+            }
+        }
+    }
+    info!(
+        recorded = summary.recorded,
+        failed = summary.failed,
+        "Recorded observations"
+    );
+    Ok(summary)
+}
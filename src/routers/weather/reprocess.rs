@@ -0,0 +1,113 @@
+use sqlx::PgPool;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::weather_client::{Coordinate, Datum, ProviderForecast, WeatherProviderKind};
+
+use super::archive::decompress_payload;
+use super::filter::ForecastFilter;
+use super::storage::{parse_forecast_data, ForecastParseError};
+
+#[derive(Debug, Default)]
+pub struct ReprocessSummary {
+    pub processed: usize,
+    pub failed: usize,
+}
+
+/// Re-runs the parsing in `storage` over archived raw forecasts and upserts the
+/// results under their original issue time, so each archived response replaces
+/// only its own forecast version. Error responses, archived as received, are
+/// left out. A response that still fails to parse is logged and counted instead
+/// of aborting the run. `user_id` limits the run to one user's responses; every
+/// user's are reprocessed without it.
+#[tracing::instrument(name = "Reprocess raw forecasts", skip(filter, pool))]
+pub async fn reprocess_raw_forecasts(
+    user_id: Option<Uuid>,
+    filter: &ForecastFilter,
+    pool: &PgPool,
+) -> Result<ReprocessSummary, ForecastParseError> {
+    let latitude = filter.location.as_ref().map(|location| location.latitude);
+    let longitude = filter.location.as_ref().map(|location| location.longitude);
+    let archived = sqlx::query!(
+        r#"
+        SELECT id, user_id, provider, latitude, longitude, city_name, http_status, requested_at
+        FROM forecast_raw
+        WHERE ($1::TIMESTAMPTZ IS NULL OR requested_at >= $1)
+            AND ($2::TIMESTAMPTZ IS NULL OR requested_at < $2)
+            AND ($3::FLOAT IS NULL OR latitude = $3)
+            AND ($4::FLOAT IS NULL OR longitude = $4)
+            AND ($5::UUID IS NULL OR user_id = $5)
+            AND http_status BETWEEN 200 AND 299
+        ORDER BY requested_at
+        "#,
+        filter.from,
+        filter.to,
+        latitude,
+        longitude,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ForecastParseError::DatabaseError(e.into()))?;
+
+    let mut summary = ReprocessSummary::default();
+    for row in archived {
+        let Some(user_id) = row.user_id else {
+            error!(raw_id = %row.id, "Archived forecast has no user, skipping");
+            summary.failed += 1;
+            continue;
+        };
+        let provider = match WeatherProviderKind::try_from(row.provider) {
+            Ok(provider) => provider,
+            Err(e) => {
+                error!(raw_id = %row.id, "Archived forecast skipped, details: {}", e);
+                summary.failed += 1;
+                continue;
+            }
+        };
+        let payload = sqlx::query_scalar!("SELECT payload FROM forecast_raw WHERE id = $1", row.id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| ForecastParseError::DatabaseError(e.into()))?;
+        let location = Coordinate {
+            latitude: row.latitude,
+            longitude: row.longitude,
+            datum: Datum::Wgs84,
+        };
+        let result = match decompress_payload(&payload) {
+            Ok(body) => {
+                let forecast = ProviderForecast {
+                    provider,
+                    body,
+                    http_status: row.http_status as u16,
+                    requested_at: row.requested_at,
+                };
+                parse_forecast_data(
+                    &forecast,
+                    &location,
+                    row.city_name.unwrap_or_default(),
+                    &user_id,
+                    pool,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => summary.processed += 1,
+            Err(ForecastParseError::DatabaseError(e)) => {
+                return Err(ForecastParseError::DatabaseError(e))
+            }
+            Err(e) => {
+                error!(raw_id = %row.id, "Failed to reprocess archived forecast, details: {}", e);
+                summary.failed += 1;
+            }
+        }
+    }
+    info!(
+        processed = summary.processed,
+        failed = summary.failed,
+        "Reprocessed archived forecasts"
+    );
+    Ok(summary)
+}
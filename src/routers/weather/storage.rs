@@ -1,52 +1,53 @@
 use chrono::{DateTime, ParseError, Utc};
-use serde::Deserialize;
-use serde_json::Value;
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 use thiserror::Error;
 use uuid::Uuid;
 
-use crate::{errors::DbError, weather_client::Coordinate};
+use crate::{
+    errors::DbError,
+    weather_client::{Coordinate, HourlyForecast, ProviderForecast, STORED_UNITS},
+};
 
-#[derive(Deserialize, Debug)]
-struct WeatherForecastResponse {
-    timelines: Timelines,
-}
-#[derive(Deserialize, Debug)]
-struct Timelines {
-    hourly: Vec<WeatherData>,
-}
-#[derive(Deserialize, Debug)]
-struct WeatherData {
-    time: DateTime<Utc>,
-    values: WeatherValues,
+struct WeatherInfoData {
+    user_id: Uuid,
+    latitude: f64,
+    longitude: f64,
+    city_name: String,
+    provider: &'static str,
+    forecast_issued_at: DateTime<Utc>,
+    values: HourlyForecast,
 }
-#[derive(Deserialize, Debug)]
-struct WeatherValues {
-    #[serde(rename = "precipitationProbability")]
-    precipitation_probability: f64,
-    #[serde(rename = "sleetIntensity")]
-    sleet_intensity: f64,
-    #[serde(rename = "snowIntensity")]
-    snow_intensity: f64,
-    temperature: f64,
-    #[serde(rename = "temperatureApparent")]
-    temperature_apparent: f64,
-    #[serde(rename = "windSpeed")]
-    wind_speed: f64,
+
+/// Minutely nowcasts are only useful for the next hour, so older rows are purged.
+const MINUTELY_RETENTION_HOURS: i32 = 2;
+
+struct WeatherDailyData {
+    user_id: Uuid,
+    latitude: f64,
+    longitude: f64,
+    city_name: String,
+    provider: &'static str,
+    temperature_min: Option<f64>,
+    temperature_max: Option<f64>,
+    sunrise_time: Option<DateTime<Utc>>,
+    sunset_time: Option<DateTime<Utc>>,
+    precipitation_accumulation: Option<f64>,
+    weather_code: Option<i32>,
+    forecast_time: DateTime<Utc>,
+    forecast_issued_at: DateTime<Utc>,
 }
 
-struct WeatherInfoData {
+struct WeatherMinutelyData {
     user_id: Uuid,
     latitude: f64,
     longitude: f64,
     city_name: String,
-    precipitation_probability: f64,
-    sleet_intensity: f64,
-    snow_intensity: f64,
-    temperature: f64,
-    temperature_apparent: f64,
-    wind_speed: f64,
+    provider: &'static str,
+    precipitation_intensity: f64,
+    precipitation_type: Option<&'static str>,
+    precipitation_probability: Option<f64>,
     forecast_time: DateTime<Utc>,
+    forecast_issued_at: DateTime<Utc>,
 }
 
 #[derive(Error, Debug)]
@@ -57,71 +58,298 @@ pub enum ForecastParseError {
     DatabaseError(#[from] DbError),
     #[error("Json data parse Error: {0}")]
     JsonParseError(#[from] serde_json::Error),
+    #[error("Raw forecast compression error: {0}")]
+    CompressionError(#[from] std::io::Error),
+}
+
+/// Whether `user_id` already has the forecast issued at `forecast_issued_at`
+/// for `location`.
+#[tracing::instrument(name = "Check forecast version", skip(location, pool))]
+pub async fn forecast_version_stored(
+    user_id: &Uuid,
+    location: &Coordinate,
+    forecast_issued_at: DateTime<Utc>,
+    pool: &PgPool,
+) -> Result<bool, ForecastParseError> {
+    let stored = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM weather_info
+            WHERE user_id = $1 AND latitude = $2 AND longitude = $3 AND forecast_issued_at = $4
+        ) AS "stored!"
+        "#,
+        user_id,
+        location.latitude,
+        location.longitude,
+        forecast_issued_at,
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| ForecastParseError::DatabaseError(e.into()))?;
+    Ok(stored)
 }
 
+/// Saves the hourly, daily and minutely rows of one response in a single
+/// transaction, so a failure part way leaves none of them behind.
 #[tracing::instrument(
     name = "Parse forecast data",
-    skip(json_data, location, city_name, pool)
+    skip(forecast, location, city_name, pool)
 )]
 pub async fn parse_forecast_data(
-    json_data: Value,
+    forecast: &ProviderForecast,
     location: &Coordinate,
     city_name: String,
     user_id: &Uuid,
     pool: &PgPool,
 ) -> Result<(), ForecastParseError> {
-    let forecast_data: WeatherForecastResponse =
-        serde_json::from_value(json_data).map_err(|e| ForecastParseError::JsonParseError(e))?;
-    for weather_data in forecast_data.timelines.hourly {
+    let provider = forecast.provider.as_str();
+    let forecast_issued_at = forecast.requested_at;
+    let forecast_data = forecast
+        .parse()
+        .map_err(ForecastParseError::JsonParseError)?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| ForecastParseError::DatabaseError(e.into()))?;
+    for weather_data in forecast_data.hourly {
         let weather_info_data = WeatherInfoData {
             user_id: *user_id,
             latitude: location.latitude,
             longitude: location.longitude,
             city_name: city_name.clone(),
-            precipitation_probability: weather_data.values.precipitation_probability,
-            sleet_intensity: weather_data.values.sleet_intensity,
-            snow_intensity: weather_data.values.snow_intensity,
-            temperature: weather_data.values.temperature,
-            temperature_apparent: weather_data.values.temperature_apparent,
-            wind_speed: weather_data.values.wind_speed,
-            forecast_time: weather_data.time,
+            provider,
+            forecast_issued_at,
+            values: weather_data,
         };
-        save_weather_data(weather_info_data, pool).await?;
+        save_weather_data(weather_info_data, &mut transaction).await?;
     }
+    for daily_data in forecast_data.daily {
+        let weather_daily_data = WeatherDailyData {
+            user_id: *user_id,
+            latitude: location.latitude,
+            longitude: location.longitude,
+            city_name: city_name.clone(),
+            provider,
+            temperature_min: daily_data.temperature_min,
+            temperature_max: daily_data.temperature_max,
+            sunrise_time: daily_data.sunrise_time,
+            sunset_time: daily_data.sunset_time,
+            precipitation_accumulation: daily_data.precipitation_accumulation,
+            weather_code: daily_data.weather_code,
+            forecast_time: daily_data.time,
+            forecast_issued_at,
+        };
+        save_weather_daily_data(weather_daily_data, &mut transaction).await?;
+    }
+    if !forecast_data.minutely.is_empty() {
+        for minutely_data in forecast_data.minutely {
+            let weather_minutely_data = WeatherMinutelyData {
+                user_id: *user_id,
+                latitude: location.latitude,
+                longitude: location.longitude,
+                city_name: city_name.clone(),
+                provider,
+                precipitation_intensity: minutely_data.precipitation_intensity,
+                precipitation_type: minutely_data
+                    .precipitation_type
+                    .map(|precipitation_type| precipitation_type.as_str()),
+                precipitation_probability: minutely_data.precipitation_probability,
+                forecast_time: minutely_data.time,
+                forecast_issued_at,
+            };
+            save_weather_minutely_data(weather_minutely_data, &mut transaction).await?;
+        }
+        purge_expired_minutely_data(&mut transaction).await?;
+    }
+    transaction
+        .commit()
+        .await
+        .map_err(|e| ForecastParseError::DatabaseError(e.into()))?;
     Ok(())
 }
 
-#[tracing::instrument(name = "Save weather data", skip(data, pool))]
-async fn save_weather_data(data: WeatherInfoData, pool: &PgPool) -> Result<(), ForecastParseError> {
+/// Every issued forecast is kept as its own row, keyed by `forecast_issued_at`.
+/// Saving the same issue again, e.g. when reprocessing, updates it in place.
+#[tracing::instrument(name = "Save weather data", skip(data, transaction))]
+async fn save_weather_data(
+    data: WeatherInfoData,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), ForecastParseError> {
     let id = Uuid::new_v4();
+    let values = data.values;
     sqlx::query!(
         r#"
         INSERT INTO weather_info
-            (id, user_id, latitude, longitude, city_name, precipitation_probability, sleet_intensity,snow_intensity,temperature,temperature_apparent,wind_speed,forecast_time)
+            (id, user_id, latitude, longitude, city_name, provider, forecast_time,
+             precipitation_probability, rain_intensity, freezing_rain_intensity, sleet_intensity, snow_intensity,
+             temperature, temperature_apparent, dew_point, humidity,
+             wind_speed, wind_direction, wind_gust,
+             pressure_surface_level, uv_index, visibility,
+             cloud_cover, cloud_base, cloud_ceiling, weather_code, forecast_issued_at, units, location_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28,
+                (SELECT id FROM locations WHERE latitude = $3 AND longitude = $4))
+            ON CONFLICT (user_id, forecast_time, latitude, longitude, forecast_issued_at) DO UPDATE
+            SET
+                provider = $6,
+                precipitation_probability = $8,
+                rain_intensity = $9,
+                freezing_rain_intensity = $10,
+                sleet_intensity = $11,
+                snow_intensity = $12,
+                temperature = $13,
+                temperature_apparent = $14,
+                dew_point = $15,
+                humidity = $16,
+                wind_speed = $17,
+                wind_direction = $18,
+                wind_gust = $19,
+                pressure_surface_level = $20,
+                uv_index = $21,
+                visibility = $22,
+                cloud_cover = $23,
+                cloud_base = $24,
+                cloud_ceiling = $25,
+                weather_code = $26,
+                units = $28,
+                location_id = EXCLUDED.location_id,
+                updated_at = CURRENT_TIMESTAMP
+        "#,
+        id,
+        data.user_id,
+        data.latitude,
+        data.longitude,
+        data.city_name,
+        data.provider,
+        values.time.naive_utc(),
+        values.precipitation_probability,
+        values.rain_intensity,
+        values.freezing_rain_intensity,
+        values.sleet_intensity,
+        values.snow_intensity,
+        values.temperature,
+        values.temperature_apparent,
+        values.dew_point,
+        values.humidity,
+        values.wind_speed,
+        values.wind_direction,
+        values.wind_gust,
+        values.pressure_surface_level,
+        values.uv_index,
+        values.visibility,
+        values.cloud_cover,
+        values.cloud_base,
+        values.cloud_ceiling,
+        values.weather_code,
+        data.forecast_issued_at,
+        STORED_UNITS,
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|e| ForecastParseError::DatabaseError(e.into()))?;
+
+    Ok(())
+}
+
+/// Kept per issue like hourly forecasts, see `save_weather_data`.
+#[tracing::instrument(name = "Save weather daily data", skip(data, transaction))]
+async fn save_weather_daily_data(
+    data: WeatherDailyData,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), ForecastParseError> {
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO weather_daily
+            (id, user_id, latitude, longitude, city_name, provider, temperature_min, temperature_max, sunrise_time, sunset_time, precipitation_accumulation, weather_code, forecast_time, forecast_issued_at, units)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            ON CONFLICT (user_id, forecast_time, latitude, longitude, forecast_issued_at) DO UPDATE
+            SET
+                provider = $6,
+                temperature_min = $7,
+                temperature_max = $8,
+                sunrise_time = $9,
+                sunset_time = $10,
+                precipitation_accumulation = $11,
+                weather_code = $12,
+                units = $15,
+                updated_at = CURRENT_TIMESTAMP
+        "#,
+        id,
+        data.user_id,
+        data.latitude,
+        data.longitude,
+        data.city_name,
+        data.provider,
+        data.temperature_min,
+        data.temperature_max,
+        data.sunrise_time.map(|time| time.naive_utc()),
+        data.sunset_time.map(|time| time.naive_utc()),
+        data.precipitation_accumulation,
+        data.weather_code,
+        data.forecast_time.naive_utc(),
+        data.forecast_issued_at,
+        STORED_UNITS,
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|e| ForecastParseError::DatabaseError(e.into()))?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Save weather minutely data", skip(data, transaction))]
+async fn save_weather_minutely_data(
+    data: WeatherMinutelyData,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), ForecastParseError> {
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO weather_minutely
+            (id, user_id, latitude, longitude, city_name, provider, precipitation_intensity, precipitation_type, precipitation_probability, forecast_time, forecast_issued_at, units)
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
-            ON CONFLICT (user_id, forecast_time, latitude, longitude) DO UPDATE 
-            SET 
-                precipitation_probability = $6,
-                sleet_intensity = $7,
-                snow_intensity = $8,
-                temperature = $9,
-                temperature_apparent = $10,
-                wind_speed = $11
+            ON CONFLICT (user_id, forecast_time, latitude, longitude, forecast_issued_at) DO UPDATE
+            SET
+                provider = $6,
+                precipitation_intensity = $7,
+                precipitation_type = $8,
+                precipitation_probability = $9,
+                units = $12
         "#,
         id,
         data.user_id,
         data.latitude,
         data.longitude,
         data.city_name,
+        data.provider,
+        data.precipitation_intensity,
+        data.precipitation_type,
         data.precipitation_probability,
-        data.sleet_intensity,
-        data.snow_intensity,
-        data.temperature,
-        data.temperature_apparent,
-        data.wind_speed,
         data.forecast_time.naive_utc(),
-    ).execute(pool).await.map_err(|e| ForecastParseError::DatabaseError(e.into())
-)?;
+        data.forecast_issued_at,
+        STORED_UNITS,
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|e| ForecastParseError::DatabaseError(e.into()))?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Purge expired minutely data", skip(transaction))]
+async fn purge_expired_minutely_data(
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), ForecastParseError> {
+    sqlx::query!(
+        r#"
+        DELETE FROM weather_minutely
+        WHERE forecast_time < (NOW() AT TIME ZONE 'UTC') - make_interval(hours => $1)
+        "#,
+        MINUTELY_RETENTION_HOURS,
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|e| ForecastParseError::DatabaseError(e.into()))?;
 
     Ok(())
 }
@@ -0,0 +1,101 @@
+use sqlx::PgPool;
+
+use crate::errors::DbError;
+
+use super::filter::ForecastFilter;
+
+/// Error statistics of one provider's forecasts of one variable at one lead
+/// time, in the variable's own unit. Bias is forecast minus observed.
+#[derive(Debug)]
+pub struct VerificationStat {
+    pub provider: String,
+    pub variable: String,
+    pub lead_hours: i32,
+    pub samples: i64,
+    pub bias: f64,
+    pub mae: f64,
+    pub rmse: f64,
+}
+
+/// Joins every stored forecast version against the same provider's
+/// observations for the same location and hour, in the same unit system, and
+/// aggregates the errors per provider, variable and whole hours between issue
+/// and forecast time. A forecast stored for several users counts once.
+/// Observations are assigned to the nearest hour and averaged when there are
+/// several. The filter window applies to forecast time.
+#[tracing::instrument(name = "Verify forecasts", skip(filter, pool))]
+pub async fn verify_forecasts(
+    filter: &ForecastFilter,
+    pool: &PgPool,
+) -> Result<Vec<VerificationStat>, DbError> {
+    let latitude = filter.location.as_ref().map(|location| location.latitude);
+    let longitude = filter.location.as_ref().map(|location| location.longitude);
+    let stats = sqlx::query_as!(
+        VerificationStat,
+        r#"
+        WITH forecasts AS (
+            SELECT DISTINCT ON (provider, latitude, longitude, forecast_time, forecast_issued_at) *
+            FROM weather_info
+            WHERE ($1::TIMESTAMPTZ IS NULL OR (forecast_time AT TIME ZONE 'UTC') >= $1)
+                AND ($2::TIMESTAMPTZ IS NULL OR (forecast_time AT TIME ZONE 'UTC') < $2)
+                AND ($3::FLOAT IS NULL OR latitude = $3)
+                AND ($4::FLOAT IS NULL OR longitude = $4)
+            ORDER BY provider, latitude, longitude, forecast_time, forecast_issued_at, id
+        ),
+        observed AS (
+            SELECT provider, latitude, longitude,
+                date_trunc('hour', observed_at + INTERVAL '30 minutes') AS forecast_time,
+                units,
+                AVG(temperature) AS temperature,
+                AVG(temperature_apparent) AS temperature_apparent,
+                AVG(dew_point) AS dew_point,
+                AVG(humidity) AS humidity,
+                AVG(wind_speed) AS wind_speed,
+                AVG(wind_gust) AS wind_gust,
+                AVG(pressure_surface_level) AS pressure_surface_level,
+                AVG(visibility) AS visibility,
+                AVG(cloud_cover) AS cloud_cover,
+                AVG(rain_intensity) AS rain_intensity
+            FROM observations
+            GROUP BY 1, 2, 3, 4, 5
+        )
+        SELECT
+            COALESCE(f.provider, 'unknown') AS "provider!",
+            v.variable AS "variable!",
+            FLOOR(EXTRACT(EPOCH FROM (f.forecast_time AT TIME ZONE 'UTC') - f.forecast_issued_at) / 3600)::INT AS "lead_hours!",
+            COUNT(*) AS "samples!",
+            AVG(v.forecast - v.observed) AS "bias!",
+            AVG(ABS(v.forecast - v.observed)) AS "mae!",
+            SQRT(AVG((v.forecast - v.observed) ^ 2)) AS "rmse!"
+        FROM forecasts f
+        JOIN observed o
+            ON o.provider = f.provider
+                AND o.latitude = f.latitude AND o.longitude = f.longitude AND o.forecast_time = f.forecast_time
+                AND o.units = f.units
+        CROSS JOIN LATERAL (VALUES
+            ('temperature', f.temperature, o.temperature),
+            ('temperature_apparent', f.temperature_apparent, o.temperature_apparent),
+            ('dew_point', f.dew_point, o.dew_point),
+            ('humidity', f.humidity, o.humidity),
+            ('wind_speed', f.wind_speed, o.wind_speed),
+            ('wind_gust', f.wind_gust, o.wind_gust),
+            ('pressure_surface_level', f.pressure_surface_level, o.pressure_surface_level),
+            ('visibility', f.visibility, o.visibility),
+            ('cloud_cover', f.cloud_cover, o.cloud_cover),
+            ('rain_intensity', f.rain_intensity, o.rain_intensity)
+        ) AS v(variable, forecast, observed)
+        WHERE v.forecast IS NOT NULL AND v.observed IS NOT NULL
+            AND (f.forecast_time AT TIME ZONE 'UTC') >= f.forecast_issued_at
+        GROUP BY 1, 2, 3
+        ORDER BY 1, 2, 3
+        "#,
+        filter.from,
+        filter.to,
+        latitude,
+        longitude,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(stats)
+}
@@ -0,0 +1,176 @@
+use std::{sync::Arc, time::Duration};
+
+use rand::Rng;
+use sqlx::PgPool;
+use tokio::{
+    sync::Semaphore,
+    task::JoinSet,
+    time::{interval_at, sleep, Instant, MissedTickBehavior},
+};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    configuration::{SchedulerSettings, Settings},
+    errors::DbError,
+    routers::{cached_or_refresh_forecast, record_observations, store_forecast},
+    start_up::get_connection_pool,
+    weather_client::{Coordinate, Datum, WeatherClient},
+};
+
+#[derive(Debug, Default)]
+pub struct RefreshSummary {
+    pub refreshed: usize,
+    pub failed: usize,
+}
+
+/// Periodically refreshes the forecast of every tracked location, i.e. every
+/// location a user is subscribed to, without waiting for a client to POST
+/// `/update_weather`.
+pub struct Scheduler {
+    settings: SchedulerSettings,
+    /// A forecast archived this recently is reused instead of fetched.
+    cache_ttl: chrono::Duration,
+    connect_pool: PgPool,
+    weather_client: WeatherClient,
+}
+
+/// A tracked location together with every user subscribed to it.
+struct TrackedLocation {
+    user_ids: Vec<Uuid>,
+    location: Coordinate,
+    city_name: String,
+}
+
+impl Scheduler {
+    pub fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            settings: configuration.scheduler,
+            cache_ttl: chrono::Duration::seconds(configuration.forecasts.cache_ttl_seconds as i64),
+            connect_pool: get_connection_pool(configuration.database),
+            weather_client: configuration.weather_client.client()?,
+        })
+    }
+
+    pub async fn run_until_stopped(self) -> Result<(), anyhow::Error> {
+        if !self.settings.enabled {
+            info!("Scheduler is disabled");
+            return std::future::pending().await;
+        }
+        // The first cycle waits a full period, so restarting the service does
+        // not refresh every tracked location at once.
+        let period = Duration::from_secs(self.settings.interval_seconds);
+        let mut ticker = interval_at(Instant::now() + period, period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            // A failed cycle is retried on the next tick instead of stopping the scheduler.
+            if let Err(e) = self.run_once().await {
+                error!("Scheduled refresh failed, details: {}", e);
+            }
+        }
+    }
+
+    /// Refreshes every tracked location once, at most `max_concurrency` at a
+    /// time and each after a random delay of up to `jitter_seconds`. A
+    /// forecast archived within `forecasts.cache_ttl_seconds` is reused like
+    /// it is for `/update_weather`. The forecast is stored for every user of
+    /// the location.
+    #[tracing::instrument(name = "Scheduled refresh", skip(self))]
+    pub async fn run_once(&self) -> Result<RefreshSummary, anyhow::Error> {
+        let tracked = tracked_locations(&self.connect_pool).await?;
+        let semaphore = Arc::new(Semaphore::new(self.settings.max_concurrency.max(1)));
+        let mut tasks = JoinSet::new();
+        for tracked in tracked {
+            let semaphore = semaphore.clone();
+            let weather_client = self.weather_client.clone();
+            let connect_pool = self.connect_pool.clone();
+            let cache_ttl = self.cache_ttl;
+            let jitter = match self.settings.jitter_seconds {
+                0 => Duration::ZERO,
+                jitter => Duration::from_millis(rand::thread_rng().gen_range(0..jitter * 1000)),
+            };
+            tasks.spawn(async move {
+                sleep(jitter).await;
+                let _permit = semaphore.acquire_owned().await?;
+                let (first_user, other_users) = tracked
+                    .user_ids
+                    .split_first()
+                    .expect("a tracked location has at least one user");
+                let served = cached_or_refresh_forecast(
+                    &weather_client,
+                    &tracked.location,
+                    tracked.city_name.clone(),
+                    first_user,
+                    cache_ttl,
+                    &connect_pool,
+                )
+                .await?;
+                for user_id in other_users {
+                    store_forecast(
+                        &served.forecast,
+                        &tracked.location,
+                        tracked.city_name.clone(),
+                        user_id,
+                        &connect_pool,
+                    )
+                    .await?;
+                }
+                Ok::<(), anyhow::Error>(())
+            });
+        }
+
+        let mut summary = RefreshSummary::default();
+        while let Some(outcome) = tasks.join_next().await {
+            match outcome {
+                Ok(Ok(())) => summary.refreshed += 1,
+                Ok(Err(e)) => {
+                    error!("Failed to refresh tracked location, details: {}", e);
+                    summary.failed += 1;
+                }
+                Err(e) => {
+                    error!("Refresh task failed to complete, details: {}", e);
+                    summary.failed += 1;
+                }
+            }
+        }
+        info!(
+            refreshed = summary.refreshed,
+            failed = summary.failed,
+            "Refreshed tracked locations"
+        );
+        if self.settings.record_observations {
+            record_observations(&self.weather_client, &self.connect_pool).await?;
+        }
+        Ok(summary)
+    }
+}
+
+#[tracing::instrument(name = "Fetch tracked locations", skip(pool))]
+async fn tracked_locations(pool: &PgPool) -> Result<Vec<TrackedLocation>, DbError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT l.latitude, l.longitude, l.name,
+            ARRAY_AGG(s.user_id ORDER BY s.created_at) AS "user_ids!"
+        FROM user_location_subscriptions s
+        JOIN locations l ON l.id = s.location_id
+        GROUP BY l.id
+        ORDER BY l.id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| TrackedLocation {
+            user_ids: row.user_ids,
+            location: Coordinate {
+                latitude: row.latitude,
+                longitude: row.longitude,
+                datum: Datum::Wgs84,
+            },
+            city_name: row.name,
+        })
+        .collect())
+}
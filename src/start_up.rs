@@ -12,8 +12,13 @@ use tower::ServiceBuilder;
 use tower_sessions::{cookie, Expiry, MemoryStore, SessionManagerLayer};
 
 use crate::{
-    configuration::{DatabaseSettings, Settings},
-    routers::{admin_dashboard, home, log_out, login, login_form, update_weather_data},
+    configuration::{DatabaseSettings, ForecastSettings, Settings},
+    routers::{
+        admin_dashboard, admin_reprocess, create_location, delete_location, edit_location,
+        export_forecasts, forecast_calendar, forecasts_geojson, geocode_places, get_location, home,
+        list_forecasts, list_locations, log_out, login, login_form, nearest_forecast,
+        update_weather_data,
+    },
     weather_client::WeatherClient,
 };
 
@@ -37,15 +42,17 @@ pub struct Server {
 pub struct AppState {
     pub connect_pool: Pool<Postgres>,
     pub weather_client: WeatherClient,
+    pub forecast_settings: ForecastSettings,
 }
 
 impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
         let connect_pool = get_connection_pool(configuration.database);
-        let weather_client = configuration.weather_client.client();
+        let weather_client = configuration.weather_client.client()?;
         let shared_state = AppState {
             connect_pool,
             weather_client,
+            forecast_settings: configuration.forecasts,
         };
         let address = format!(
             "{}:{}",
@@ -64,7 +71,24 @@ impl Application {
         let admin_router = Router::new()
             .route("/", get(home))
             .route("/dashboard", get(admin_dashboard))
-            .route("/logout", post(log_out));
+            .route("/logout", post(log_out))
+            .route("/reprocess", post(admin_reprocess));
+
+        let locations_router = Router::new()
+            .route("/", get(list_locations).post(create_location))
+            .route(
+                "/:location_id",
+                get(get_location).put(edit_location).delete(delete_location),
+            );
+
+        let api_router = Router::new()
+            .nest("/locations", locations_router)
+            .route("/geocode", get(geocode_places))
+            .route("/forecasts", get(list_forecasts))
+            .route("/forecasts.geojson", get(forecasts_geojson))
+            .route("/forecasts/nearest", get(nearest_forecast))
+            .route("/forecasts/export", get(export_forecasts))
+            .route("/calendar/:token/:file", get(forecast_calendar));
 
         let router = Router::new()
             .route("/", get(home))
@@ -72,6 +96,7 @@ impl Application {
             .nest("/login", login_router)
             .nest("/admin", admin_router)
             .route("/update_weather", post(update_weather_data))
+            .nest("/api/v1", api_router)
             .layer(
                 ServiceBuilder::new()
                     .layer(session_layer)
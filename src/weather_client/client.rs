@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde_json::Value;
+use tracing::warn;
+
+use super::{Coordinate, Forecast, Observation, WeatherProvider, WeatherProviderKind};
+
+/// Forecast response body, as received, together with the provider that
+/// served it. Error responses are kept too, so they can be archived.
+pub struct ProviderForecast {
+    pub provider: WeatherProviderKind,
+    pub body: Vec<u8>,
+    pub http_status: u16,
+    pub requested_at: DateTime<Utc>,
+}
+
+impl ProviderForecast {
+    pub fn is_success(&self) -> bool {
+        StatusCode::from_u16(self.http_status).is_ok_and(|status| status.is_success())
+    }
+
+    pub fn parse(&self) -> Result<Forecast, serde_json::Error> {
+        self.provider
+            .parse_forecast(serde_json::from_slice(&self.body)?)
+    }
+}
+
+/// Every forecast response received while trying providers in order; all but
+/// the last were passed over for the next provider.
+pub struct ForecastAttempts {
+    pub responses: Vec<ProviderForecast>,
+    /// Set when the last provider tried gave no response at all.
+    pub error: Option<reqwest::Error>,
+}
+
+impl ForecastAttempts {
+    /// The response to use, i.e. the last one, unless the last provider
+    /// tried gave none.
+    pub fn served(mut self) -> Result<ProviderForecast, reqwest::Error> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self
+                .responses
+                .pop()
+                .expect("a response is kept unless there is an error")),
+        }
+    }
+}
+
+/// Current-conditions response body together with the provider that served
+/// it.
+pub struct ProviderObservation {
+    pub provider: WeatherProviderKind,
+    pub body: Vec<u8>,
+    pub http_status: u16,
+    pub requested_at: DateTime<Utc>,
+}
+
+impl ProviderObservation {
+    pub fn is_success(&self) -> bool {
+        StatusCode::from_u16(self.http_status).is_ok_and(|status| status.is_success())
+    }
+
+    pub fn parse(&self) -> Result<Observation, serde_json::Error> {
+        self.provider
+            .parse_observation(serde_json::from_slice(&self.body)?)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Endpoint {
+    Forecast,
+    Observation,
+}
+
+impl Endpoint {
+    fn parse(self, provider: WeatherProviderKind, body: &[u8]) -> Result<(), serde_json::Error> {
+        let json_data: Value = serde_json::from_slice(body)?;
+        match self {
+            Endpoint::Forecast => provider.parse_forecast(json_data).map(|_| ()),
+            Endpoint::Observation => provider.parse_observation(json_data).map(|_| ()),
+        }
+    }
+}
+
+/// One provider response, whichever endpoint it came from.
+struct Attempt {
+    provider: WeatherProviderKind,
+    body: Vec<u8>,
+    http_status: u16,
+    requested_at: DateTime<Utc>,
+}
+
+/// Queries providers in order, falling through to the next one when a
+/// provider times out, is unreachable, answers 5xx, rate limits with 429 or
+/// answers with a body that does not parse.
+/// The last provider's response is served whatever its status.
+#[derive(Clone)]
+pub struct WeatherClient {
+    providers: Vec<Arc<dyn WeatherProvider>>,
+}
+
+impl WeatherClient {
+    pub fn new(providers: Vec<Arc<dyn WeatherProvider>>) -> Self {
+        assert!(
+            !providers.is_empty(),
+            "WeatherClient needs at least one provider"
+        );
+        Self { providers }
+    }
+
+    pub fn provider_kinds(&self) -> Vec<WeatherProviderKind> {
+        self.providers
+            .iter()
+            .map(|provider| provider.kind())
+            .collect()
+    }
+
+    pub async fn get_weather_forecast(&self, location: &Coordinate) -> ForecastAttempts {
+        let (attempts, error) = self.fetch(location, Endpoint::Forecast).await;
+        ForecastAttempts {
+            responses: attempts
+                .into_iter()
+                .map(|attempt| ProviderForecast {
+                    provider: attempt.provider,
+                    body: attempt.body,
+                    http_status: attempt.http_status,
+                    requested_at: attempt.requested_at,
+                })
+                .collect(),
+            error,
+        }
+    }
+
+    /// Observations are not archived, so only the response served is returned.
+    pub async fn get_observation(
+        &self,
+        location: &Coordinate,
+    ) -> Result<ProviderObservation, reqwest::Error> {
+        let (mut attempts, error) = self.fetch(location, Endpoint::Observation).await;
+        if let Some(err) = error {
+            return Err(err);
+        }
+        let attempt = attempts
+            .pop()
+            .expect("a response is kept unless there is an error");
+        Ok(ProviderObservation {
+            provider: attempt.provider,
+            body: attempt.body,
+            http_status: attempt.http_status,
+            requested_at: attempt.requested_at,
+        })
+    }
+
+    /// Returns every response received, in order, and the error of the last
+    /// provider tried when it gave no response at all.
+    async fn fetch(
+        &self,
+        location: &Coordinate,
+        endpoint: Endpoint,
+    ) -> (Vec<Attempt>, Option<reqwest::Error>) {
+        // Providers expect WGS-84, whatever datum the user submitted.
+        let location = &location.to_wgs84();
+        let mut attempts = Vec::new();
+        let mut providers = self.providers.iter().peekable();
+        while let Some(provider) = providers.next() {
+            let has_next = providers.peek().is_some();
+            let requested_at = Utc::now();
+            let (status, body) = match request(provider.as_ref(), location, endpoint).await {
+                Ok(response) => response,
+                Err(err) if has_next && is_failover_error(&err) => {
+                    warn!(
+                        provider = provider.kind().as_str(),
+                        "Weather provider unavailable, trying next one, details: {}", err
+                    );
+                    continue;
+                }
+                Err(err) => return (attempts, Some(err)),
+            };
+            let parse_error = match status.is_success() && has_next {
+                true => endpoint.parse(provider.kind(), &body).err(),
+                false => None,
+            };
+            attempts.push(Attempt {
+                provider: provider.kind(),
+                body,
+                http_status: status.as_u16(),
+                requested_at,
+            });
+            if has_next && is_failover_status(status) {
+                warn!(
+                    provider = provider.kind().as_str(),
+                    "Weather provider unavailable, trying next one, status: {}", status
+                );
+            } else if let Some(err) = parse_error {
+                warn!(
+                    provider = provider.kind().as_str(),
+                    "Weather provider response does not parse, trying next one, details: {}", err
+                );
+            } else {
+                break;
+            }
+        }
+        (attempts, None)
+    }
+}
+
+/// Sends one request and reads its body, so a body that times out fails
+/// over like a request that does.
+async fn request(
+    provider: &dyn WeatherProvider,
+    location: &Coordinate,
+    endpoint: Endpoint,
+) -> Result<(StatusCode, Vec<u8>), reqwest::Error> {
+    let response = match endpoint {
+        Endpoint::Forecast => provider.fetch_forecast(location).await?,
+        Endpoint::Observation => provider.fetch_observation(location).await?,
+    };
+    let status = response.status();
+    Ok((status, response.bytes().await?.to_vec()))
+}
+
+fn is_failover_error(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect()
+}
+
+fn is_failover_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
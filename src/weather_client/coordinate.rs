@@ -0,0 +1,604 @@
+use std::{f64::consts::PI, num::ParseFloatError};
+
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinate {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub datum: Datum,
+}
+
+/// The geodetic datum a coordinate is expressed in. Weather providers and
+/// the gazetteer use WGS-84, while WeChat location messages and most
+/// Chinese map apps use the obfuscated GCJ-02, and Baidu Maps uses BD-09.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Datum {
+    #[default]
+    Wgs84,
+    Gcj02,
+    Bd09,
+}
+
+impl Datum {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Datum::Wgs84 => "wgs84",
+            Datum::Gcj02 => "gcj02",
+            Datum::Bd09 => "bd09",
+        }
+    }
+}
+
+impl TryFrom<String> for Datum {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "wgs84" => Ok(Self::Wgs84),
+            "gcj02" => Ok(Self::Gcj02),
+            "bd09" => Ok(Self::Bd09),
+            other => Err(format!(
+                "{} is not a supported datum. Use either `wgs84`, `gcj02` or `bd09`.",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CoordinateParseError {
+    #[error("Coordinate is Foramt Failed")]
+    Format,
+    #[error("Coordinate is Parse Failed")]
+    ParseFloat(ParseFloatError),
+    #[error("Coordinate is Invalid Value")]
+    InvalidValue,
+    #[error("Invalid degrees-minutes-seconds coordinate: {0}")]
+    InvalidDms(String),
+    #[error("Invalid geo URI: {0}")]
+    InvalidGeoUri(String),
+    #[error("Invalid Plus Code: {0}")]
+    InvalidPlusCode(String),
+    #[error("Invalid geohash: {0}")]
+    InvalidGeohash(String),
+}
+
+/// Characters of an Open Location Code, in digit order.
+const PLUS_CODE_ALPHABET: &str = "23456789CFGHJMPQRVWX";
+/// Degrees covered by one digit of each latitude/longitude pair.
+const PLUS_CODE_PAIR_RESOLUTIONS: [f64; 5] = [20.0, 1.0, 0.05, 0.0025, 0.000125];
+const PLUS_CODE_SEPARATOR_POSITION: usize = 8;
+const GEOHASH_ALPHABET: &str = "0123456789bcdefghjkmnpqrstuvwxyz";
+/// Bare geohashes shorter than this are more likely typos than ~5 km cells.
+const MIN_BARE_GEOHASH_LENGTH: usize = 5;
+const MAX_GEOHASH_LENGTH: usize = 12;
+/// Stored coordinates are rounded to this many decimals, about 11 m, so
+/// the same place typed twice maps to the same row.
+const SNAP_DECIMALS: i32 = 4;
+
+impl Coordinate {
+    /// Parses a coordinate in any of the forms users paste or WeChat shares:
+    ///
+    /// - decimal degrees: `39.9042,116.4074`
+    /// - degrees, minutes and seconds: `39°54'N 116°23'E`, `北纬39°54′ 东经116°23′`
+    /// - geo URIs: `geo:39.9,116.4;u=35`
+    /// - full Plus Codes: `8PFRW988+22`
+    /// - geohashes: `wx4g0ec1` or `geohash:wx4g`
+    ///
+    /// Plus Codes and geohashes name an area and parse to its center. The
+    /// datum is WGS-84 unless a geo URI names another one with `crs=`.
+    pub fn parse(location: String) -> Result<Coordinate, CoordinateParseError> {
+        let location = location.trim();
+        let coordinate = if let Some(uri) = strip_prefix_ignore_case(location, "geo:") {
+            parse_geo_uri(uri)?
+        } else if let Some(hash) = strip_prefix_ignore_case(location, "geohash:") {
+            parse_geohash(hash.trim())?
+        } else if location.contains('+') && !location.contains([',', '.', ' ']) {
+            parse_plus_code(location)?
+        } else if is_dms(location) {
+            parse_dms(location)?
+        } else if location.contains(',') {
+            parse_decimal(location)?
+        } else if location.len() >= MIN_BARE_GEOHASH_LENGTH
+            && location
+                .chars()
+                .all(|c| GEOHASH_ALPHABET.contains(c.to_ascii_lowercase()))
+        {
+            parse_geohash(location)?
+        } else {
+            return Err(CoordinateParseError::Format);
+        };
+        if !(-90.0..=90.0).contains(&coordinate.latitude)
+            || !(-180.0..=180.0).contains(&coordinate.longitude)
+        {
+            return Err(CoordinateParseError::InvalidValue);
+        }
+        Ok(coordinate)
+    }
+
+    pub fn wgs84(latitude: f64, longitude: f64) -> Coordinate {
+        Coordinate {
+            latitude,
+            longitude,
+            datum: Datum::Wgs84,
+        }
+    }
+
+    /// Rounds to the precision coordinates are stored with.
+    pub fn snapped(&self) -> Coordinate {
+        let factor = 10f64.powi(SNAP_DECIMALS);
+        Coordinate {
+            latitude: (self.latitude * factor).round() / factor,
+            longitude: (self.longitude * factor).round() / factor,
+            datum: self.datum,
+        }
+    }
+
+    /// Converts to WGS-84, the datum providers and the gazetteer expect.
+    pub fn to_wgs84(&self) -> Coordinate {
+        let (latitude, longitude) = match self.datum {
+            Datum::Wgs84 => (self.latitude, self.longitude),
+            Datum::Gcj02 => gcj02_to_wgs84(self.latitude, self.longitude),
+            Datum::Bd09 => {
+                let (latitude, longitude) = bd09_to_gcj02(self.latitude, self.longitude);
+                gcj02_to_wgs84(latitude, longitude)
+            }
+        };
+        Coordinate::wgs84(latitude, longitude)
+    }
+
+    pub fn to_datum(&self, datum: Datum) -> Coordinate {
+        let wgs84 = self.to_wgs84();
+        let (latitude, longitude) = match datum {
+            Datum::Wgs84 => (wgs84.latitude, wgs84.longitude),
+            Datum::Gcj02 => wgs84_to_gcj02(wgs84.latitude, wgs84.longitude),
+            Datum::Bd09 => {
+                let (latitude, longitude) = wgs84_to_gcj02(wgs84.latitude, wgs84.longitude);
+                gcj02_to_bd09(latitude, longitude)
+            }
+        };
+        Coordinate {
+            latitude,
+            longitude,
+            datum,
+        }
+    }
+}
+
+/// Krasovsky 1940 ellipsoid parameters used by GCJ-02.
+const KRASOVSKY_SEMI_MAJOR_AXIS: f64 = 6378245.0;
+const KRASOVSKY_ECCENTRICITY_SQUARED: f64 = 0.006_693_421_622_965_943;
+const BD09_FACTOR: f64 = PI * 3000.0 / 180.0;
+/// Offset between GCJ-02 and WGS-84 is below this many degrees once the
+/// inverse conversion has converged, about 1 cm.
+const GCJ02_INVERSE_TOLERANCE: f64 = 1e-7;
+const GCJ02_INVERSE_MAX_ITERATIONS: usize = 30;
+
+/// GCJ-02 only offsets coordinates in mainland China; elsewhere it equals
+/// WGS-84.
+fn outside_china(latitude: f64, longitude: f64) -> bool {
+    !(72.004..=137.8347).contains(&longitude) || !(0.8293..=55.8271).contains(&latitude)
+}
+
+fn gcj02_offset(latitude: f64, longitude: f64) -> (f64, f64) {
+    let x = longitude - 105.0;
+    let y = latitude - 35.0;
+    let common = (20.0 * (6.0 * x * PI).sin() + 20.0 * (2.0 * x * PI).sin()) * 2.0 / 3.0;
+    let latitude_offset = -100.0
+        + 2.0 * x
+        + 3.0 * y
+        + 0.2 * y * y
+        + 0.1 * x * y
+        + 0.2 * x.abs().sqrt()
+        + common
+        + (20.0 * (y * PI).sin() + 40.0 * (y / 3.0 * PI).sin()) * 2.0 / 3.0
+        + (160.0 * (y / 12.0 * PI).sin() + 320.0 * (y * PI / 30.0).sin()) * 2.0 / 3.0;
+    let longitude_offset = 300.0
+        + x
+        + 2.0 * y
+        + 0.1 * x * x
+        + 0.1 * x * y
+        + 0.1 * x.abs().sqrt()
+        + common
+        + (20.0 * (x * PI).sin() + 40.0 * (x / 3.0 * PI).sin()) * 2.0 / 3.0
+        + (150.0 * (x / 12.0 * PI).sin() + 300.0 * (x / 30.0 * PI).sin()) * 2.0 / 3.0;
+
+    let radians = latitude.to_radians();
+    let magic = 1.0 - KRASOVSKY_ECCENTRICITY_SQUARED * radians.sin().powi(2);
+    let sqrt_magic = magic.sqrt();
+    (
+        latitude_offset * 180.0
+            / ((KRASOVSKY_SEMI_MAJOR_AXIS * (1.0 - KRASOVSKY_ECCENTRICITY_SQUARED))
+                / (magic * sqrt_magic)
+                * PI),
+        longitude_offset * 180.0 / (KRASOVSKY_SEMI_MAJOR_AXIS / sqrt_magic * radians.cos() * PI),
+    )
+}
+
+fn wgs84_to_gcj02(latitude: f64, longitude: f64) -> (f64, f64) {
+    if outside_china(latitude, longitude) {
+        return (latitude, longitude);
+    }
+    let (latitude_offset, longitude_offset) = gcj02_offset(latitude, longitude);
+    (latitude + latitude_offset, longitude + longitude_offset)
+}
+
+/// GCJ-02 has no closed-form inverse, so the WGS-84 coordinate is found by
+/// iterating the forward conversion.
+fn gcj02_to_wgs84(latitude: f64, longitude: f64) -> (f64, f64) {
+    let mut wgs84 = (latitude, longitude);
+    for _ in 0..GCJ02_INVERSE_MAX_ITERATIONS {
+        let gcj02 = wgs84_to_gcj02(wgs84.0, wgs84.1);
+        let error = (gcj02.0 - latitude, gcj02.1 - longitude);
+        if error.0.abs() < GCJ02_INVERSE_TOLERANCE && error.1.abs() < GCJ02_INVERSE_TOLERANCE {
+            break;
+        }
+        wgs84 = (wgs84.0 - error.0, wgs84.1 - error.1);
+    }
+    wgs84
+}
+
+fn gcj02_to_bd09(latitude: f64, longitude: f64) -> (f64, f64) {
+    let z = (longitude * longitude + latitude * latitude).sqrt()
+        + 0.00002 * (latitude * BD09_FACTOR).sin();
+    let theta = latitude.atan2(longitude) + 0.000003 * (longitude * BD09_FACTOR).cos();
+    (z * theta.sin() + 0.006, z * theta.cos() + 0.0065)
+}
+
+fn bd09_to_gcj02(latitude: f64, longitude: f64) -> (f64, f64) {
+    let x = longitude - 0.0065;
+    let y = latitude - 0.006;
+    let z = (x * x + y * y).sqrt() - 0.00002 * (y * BD09_FACTOR).sin();
+    let theta = y.atan2(x) - 0.000003 * (x * BD09_FACTOR).cos();
+    (z * theta.sin(), z * theta.cos())
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &text[prefix.len()..])
+}
+
+fn parse_decimal(location: &str) -> Result<Coordinate, CoordinateParseError> {
+    let parts: Vec<&str> = location.split(',').collect();
+    if parts.len() != 2 {
+        return Err(CoordinateParseError::Format);
+    };
+
+    let latitude = parts[0].trim().parse::<f64>().map_err(|e| {
+        error!("parse latitude error, details: {}", e.to_string());
+        CoordinateParseError::ParseFloat(e)
+    })?;
+    let longitude = parts[1].trim().parse::<f64>().map_err(|e| {
+        error!("parse longitude error, details: {}", e.to_string());
+        CoordinateParseError::ParseFloat(e)
+    })?;
+    Ok(Coordinate {
+        latitude,
+        longitude,
+        datum: Datum::Wgs84,
+    })
+}
+
+/// Parses an RFC 5870 `geo:` URI path. Altitude and the `u` uncertainty
+/// parameter are ignored. Besides the standard `wgs84`, `crs=gcj02` and
+/// `crs=bd09` are accepted for links from Chinese map apps.
+fn parse_geo_uri(uri: &str) -> Result<Coordinate, CoordinateParseError> {
+    let invalid = |message: &str| CoordinateParseError::InvalidGeoUri(message.to_string());
+    // Map apps append a `?q=` label after the coordinate.
+    let uri = uri.split('?').next().unwrap_or_default();
+    let mut sections = uri.split(';');
+    let coordinates = sections.next().unwrap_or_default();
+    let mut datum = Datum::Wgs84;
+    for parameter in sections {
+        if let Some((name, value)) = parameter.split_once('=') {
+            if name.trim().eq_ignore_ascii_case("crs") {
+                datum = Datum::try_from(value.trim().to_string())
+                    .map_err(CoordinateParseError::InvalidGeoUri)?;
+            }
+        }
+    }
+    let parts: Vec<&str> = coordinates.split(',').collect();
+    if !(2..=3).contains(&parts.len()) {
+        return Err(invalid("expected geo:<latitude>,<longitude>[,<altitude>]"));
+    }
+    let number = |part: &str, name: &str| {
+        part.trim()
+            .parse::<f64>()
+            .map_err(|e| CoordinateParseError::InvalidGeoUri(format!("{}: {}", name, e)))
+    };
+    Ok(Coordinate {
+        latitude: number(parts[0], "latitude")?,
+        longitude: number(parts[1], "longitude")?,
+        datum,
+    })
+}
+
+/// Decodes a full Open Location Code to the center of its area. Short codes
+/// need a reference location to recover their prefix, so they are rejected.
+fn parse_plus_code(code: &str) -> Result<Coordinate, CoordinateParseError> {
+    let invalid = |message: &str| CoordinateParseError::InvalidPlusCode(message.to_string());
+    let code = code.to_ascii_uppercase();
+    let (prefix, suffix) = code
+        .split_once('+')
+        .ok_or_else(|| invalid("missing the + separator"))?;
+    if suffix.contains('+') {
+        return Err(invalid("more than one + separator"));
+    }
+    if prefix.len() < PLUS_CODE_SEPARATOR_POSITION {
+        return Err(invalid(
+            "short codes need a reference location; send the full code",
+        ));
+    }
+    if prefix.len() > PLUS_CODE_SEPARATOR_POSITION {
+        return Err(invalid("the + separator must follow the eighth digit"));
+    }
+    if suffix.len() == 1 {
+        return Err(invalid("at least two digits must follow the + separator"));
+    }
+    let significant = prefix.trim_end_matches('0');
+    let padding = prefix.len() - significant.len();
+    if padding > 0 && (significant.len() < 2 || significant.len() % 2 != 0 || !suffix.is_empty()) {
+        return Err(invalid("misplaced 0 padding"));
+    }
+    let digits = significant
+        .chars()
+        .chain(suffix.chars())
+        .map(|c| {
+            PLUS_CODE_ALPHABET
+                .find(c)
+                .map(|value| value as f64)
+                .ok_or_else(|| {
+                    CoordinateParseError::InvalidPlusCode(format!("invalid digit {}", c))
+                })
+        })
+        .collect::<Result<Vec<f64>, _>>()?;
+    if digits[0] > 8.0 || digits[1] > 17.0 {
+        return Err(invalid("outside the valid range"));
+    }
+
+    let mut latitude = -90.0;
+    let mut longitude = -180.0;
+    let mut latitude_size = 0.0;
+    let mut longitude_size = 0.0;
+    for (pair, resolution) in digits.chunks(2).zip(PLUS_CODE_PAIR_RESOLUTIONS) {
+        latitude += pair[0] * resolution;
+        longitude += pair[1] * resolution;
+        latitude_size = resolution;
+        longitude_size = resolution;
+    }
+    // Digits after the tenth refine a 4 column by 5 row grid.
+    for value in digits.iter().skip(PLUS_CODE_PAIR_RESOLUTIONS.len() * 2) {
+        latitude_size /= 5.0;
+        longitude_size /= 4.0;
+        latitude += (value / 4.0).floor() * latitude_size;
+        longitude += (value % 4.0) * longitude_size;
+    }
+    Ok(Coordinate {
+        latitude: (latitude + latitude_size / 2.0).min(90.0),
+        longitude: longitude + longitude_size / 2.0,
+        datum: Datum::Wgs84,
+    })
+}
+
+/// Decodes a geohash to the center of its cell.
+fn parse_geohash(hash: &str) -> Result<Coordinate, CoordinateParseError> {
+    if hash.is_empty() || hash.len() > MAX_GEOHASH_LENGTH {
+        return Err(CoordinateParseError::InvalidGeohash(format!(
+            "expected 1 to {} characters",
+            MAX_GEOHASH_LENGTH
+        )));
+    }
+    let mut latitude = (-90.0, 90.0);
+    let mut longitude = (-180.0, 180.0);
+    let mut is_longitude = true;
+    for c in hash.chars() {
+        let value = GEOHASH_ALPHABET
+            .find(c.to_ascii_lowercase())
+            .ok_or_else(|| {
+                CoordinateParseError::InvalidGeohash(format!("invalid character {}", c))
+            })?;
+        for bit in (0..5).rev() {
+            let range: &mut (f64, f64) = if is_longitude {
+                &mut longitude
+            } else {
+                &mut latitude
+            };
+            let middle = (range.0 + range.1) / 2.0;
+            if value & (1 << bit) != 0 {
+                range.0 = middle;
+            } else {
+                range.1 = middle;
+            }
+            is_longitude = !is_longitude;
+        }
+    }
+    Ok(Coordinate {
+        latitude: (latitude.0 + latitude.1) / 2.0,
+        longitude: (longitude.0 + longitude.1) / 2.0,
+        datum: Datum::Wgs84,
+    })
+}
+
+/// Chinese hemisphere prefixes as shown by WeChat and Chinese map apps.
+const CHINESE_HEMISPHERES: [(&str, &str); 4] =
+    [("北纬", "N"), ("南纬", "S"), ("东经", "E"), ("西经", "W")];
+
+/// Whether `location` uses degree, minute or second marks, or separated
+/// values with compass letters on either side of their numbers. A single
+/// word like `ezs42` is a geohash instead.
+fn is_dms(location: &str) -> bool {
+    if location.contains(['°', 'º', '′', '″', '\'', '"', '纬', '经']) {
+        return true;
+    }
+    let parts: Vec<&str> = location
+        .split([',', ' '])
+        .filter(|part| !part.is_empty())
+        .collect();
+    parts.len() > 1
+        && parts.iter().any(|part| {
+            let is_hemisphere = |c: Option<char>| {
+                c.is_some_and(|c| matches!(c.to_ascii_uppercase(), 'N' | 'S' | 'E' | 'W'))
+            };
+            part.chars().any(|c| c.is_ascii_digit())
+                && (is_hemisphere(part.chars().next()) || is_hemisphere(part.chars().last()))
+        })
+}
+
+enum DmsToken {
+    Number(f64),
+    Hemisphere(char),
+    Comma,
+}
+
+/// One latitude or longitude read from degrees, minutes and seconds.
+struct DmsValue {
+    degrees: f64,
+    hemisphere: Option<char>,
+}
+
+fn parse_dms(location: &str) -> Result<Coordinate, CoordinateParseError> {
+    let tokens = tokenize_dms(location)?;
+    let has_hemispheres = tokens
+        .iter()
+        .any(|token| matches!(token, DmsToken::Hemisphere(_)));
+    let groups = if has_hemispheres {
+        group_by_hemisphere(&tokens)?
+    } else {
+        // Without compass letters, signs give the hemisphere and a comma
+        // separates latitude from longitude.
+        tokens
+            .split(|token| matches!(token, DmsToken::Comma))
+            .map(|group| dms_value(&numbers(group), None))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let [first, second] = groups.as_slice() else {
+        return Err(CoordinateParseError::InvalidDms(
+            "expected a latitude and a longitude".to_string(),
+        ));
+    };
+    let is_latitude = |value: &DmsValue| matches!(value.hemisphere, None | Some('N' | 'S'));
+    let is_longitude = |value: &DmsValue| matches!(value.hemisphere, None | Some('E' | 'W'));
+    let (latitude, longitude) = if is_latitude(first) && is_longitude(second) {
+        (first, second)
+    } else if is_longitude(first) && is_latitude(second) {
+        (second, first)
+    } else {
+        return Err(CoordinateParseError::InvalidDms(
+            "expected one N/S and one E/W hemisphere".to_string(),
+        ));
+    };
+    Ok(Coordinate {
+        latitude: latitude.degrees,
+        longitude: longitude.degrees,
+        datum: Datum::Wgs84,
+    })
+}
+
+fn tokenize_dms(location: &str) -> Result<Vec<DmsToken>, CoordinateParseError> {
+    let mut location = location.to_string();
+    for (prefix, hemisphere) in CHINESE_HEMISPHERES {
+        location = location.replace(prefix, hemisphere);
+    }
+    let mut tokens = Vec::new();
+    let mut chars = location.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '0'..='9' | '.' | '-' | '+' => {
+                let mut number = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !matches!(next, '0'..='9' | '.') {
+                        break;
+                    }
+                    number.push(next);
+                    chars.next();
+                }
+                let value = number.parse::<f64>().map_err(|e| {
+                    CoordinateParseError::InvalidDms(format!("{:?}: {}", number, e))
+                })?;
+                tokens.push(DmsToken::Number(value));
+            }
+            'N' | 'S' | 'E' | 'W' | 'n' | 's' | 'e' | 'w' => {
+                tokens.push(DmsToken::Hemisphere(c.to_ascii_uppercase()))
+            }
+            ',' | '，' => tokens.push(DmsToken::Comma),
+            '°' | 'º' | '′' | '″' | '\'' | '"' => {}
+            c if c.is_whitespace() => {}
+            c => {
+                return Err(CoordinateParseError::InvalidDms(format!(
+                    "unexpected character {:?}",
+                    c
+                )))
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Splits tokens into values at their compass letters, which either all
+/// precede (`N39 54 E116 23`) or all follow (`39 54 N 116 23 E`) the numbers.
+fn group_by_hemisphere(tokens: &[DmsToken]) -> Result<Vec<DmsValue>, CoordinateParseError> {
+    let prefixed = matches!(tokens.first(), Some(DmsToken::Hemisphere(_)));
+    let mut values = Vec::new();
+    let mut group: Vec<f64> = Vec::new();
+    let mut pending = None;
+    for token in tokens {
+        match token {
+            DmsToken::Number(value) => group.push(*value),
+            DmsToken::Hemisphere(hemisphere) if prefixed => {
+                if let Some(previous) = pending.replace(*hemisphere) {
+                    values.push(dms_value(&std::mem::take(&mut group), Some(previous))?);
+                }
+            }
+            DmsToken::Hemisphere(hemisphere) => {
+                values.push(dms_value(&std::mem::take(&mut group), Some(*hemisphere))?);
+            }
+            DmsToken::Comma => {}
+        }
+    }
+    match pending {
+        Some(hemisphere) => values.push(dms_value(&group, Some(hemisphere))?),
+        None if !group.is_empty() => {
+            return Err(CoordinateParseError::InvalidDms(
+                "every value needs a hemisphere letter".to_string(),
+            ))
+        }
+        None => {}
+    }
+    Ok(values)
+}
+
+fn numbers(tokens: &[DmsToken]) -> Vec<f64> {
+    tokens
+        .iter()
+        .filter_map(|token| match token {
+            DmsToken::Number(value) => Some(*value),
+            _ => None,
+        })
+        .collect()
+}
+
+fn dms_value(numbers: &[f64], hemisphere: Option<char>) -> Result<DmsValue, CoordinateParseError> {
+    let invalid = |message: &str| CoordinateParseError::InvalidDms(message.to_string());
+    let (degrees, minutes, seconds) = match numbers {
+        [degrees] => (*degrees, 0.0, 0.0),
+        [degrees, minutes] => (*degrees, *minutes, 0.0),
+        [degrees, minutes, seconds] => (*degrees, *minutes, *seconds),
+        _ => return Err(invalid("expected degrees, minutes and seconds")),
+    };
+    if !(0.0..60.0).contains(&minutes) || !(0.0..60.0).contains(&seconds) {
+        return Err(invalid("minutes and seconds must be between 0 and 60"));
+    }
+    if hemisphere.is_some() && degrees.is_sign_negative() {
+        return Err(invalid("a negative value cannot have a hemisphere"));
+    }
+    let magnitude = degrees.abs() + minutes / 60.0 + seconds / 3600.0;
+    let negative = degrees.is_sign_negative() || matches!(hemisphere, Some('S' | 'W'));
+    Ok(DmsValue {
+        degrees: if negative { -magnitude } else { magnitude },
+        hemisphere,
+    })
+}
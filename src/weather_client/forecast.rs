@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+
+/// Provider-neutral forecast produced by every `WeatherProvider`.
+#[derive(Debug, Default)]
+pub struct Forecast {
+    pub hourly: Vec<HourlyForecast>,
+    pub daily: Vec<DailyForecast>,
+    pub minutely: Vec<MinutelyForecast>,
+}
+
+/// The canonical unit system of stored values: °C, %, m/s, mm/hr, hPa and
+/// km. Every provider's values are converted to it before they are stored,
+/// and each stored row records it in its `units` column. Metric rather than
+/// SI (K, Pa, m), since providers report and users read these units, so
+/// stored values need no conversion in the common case.
+pub const STORED_UNITS: &str = "metric";
+
+/// Values a provider does not report are left as `None`. Units are those of
+/// `STORED_UNITS`.
+#[derive(Debug, Default)]
+pub struct HourlyForecast {
+    pub time: DateTime<Utc>,
+    pub precipitation_probability: Option<f64>,
+    pub rain_intensity: Option<f64>,
+    pub freezing_rain_intensity: Option<f64>,
+    pub sleet_intensity: Option<f64>,
+    pub snow_intensity: Option<f64>,
+    pub temperature: Option<f64>,
+    pub temperature_apparent: Option<f64>,
+    pub dew_point: Option<f64>,
+    pub humidity: Option<f64>,
+    pub wind_speed: Option<f64>,
+    /// Degrees clockwise from north the wind blows from.
+    pub wind_direction: Option<f64>,
+    pub wind_gust: Option<f64>,
+    pub pressure_surface_level: Option<f64>,
+    pub uv_index: Option<f64>,
+    pub visibility: Option<f64>,
+    pub cloud_cover: Option<f64>,
+    pub cloud_base: Option<f64>,
+    pub cloud_ceiling: Option<f64>,
+    /// Weather code in the provider's own code table.
+    pub weather_code: Option<i32>,
+}
+
+/// Conditions a provider reports as current at `time`. It carries the same
+/// variables and units as an hourly forecast so the two compare directly.
+pub type Observation = HourlyForecast;
+
+#[derive(Debug)]
+pub struct DailyForecast {
+    /// Start of the forecast day as reported by the provider.
+    pub time: DateTime<Utc>,
+    pub temperature_min: Option<f64>,
+    pub temperature_max: Option<f64>,
+    pub sunrise_time: Option<DateTime<Utc>>,
+    pub sunset_time: Option<DateTime<Utc>>,
+    /// Liquid-equivalent precipitation over the day, in mm.
+    pub precipitation_accumulation: Option<f64>,
+    /// Weather code in the provider's own code table.
+    pub weather_code: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecipitationType {
+    Rain,
+    Snow,
+    Sleet,
+    FreezingRain,
+}
+
+impl PrecipitationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrecipitationType::Rain => "rain",
+            PrecipitationType::Snow => "snow",
+            PrecipitationType::Sleet => "sleet",
+            PrecipitationType::FreezingRain => "freezing_rain",
+        }
+    }
+}
+
+/// One minute of the short-term precipitation nowcast.
+#[derive(Debug)]
+pub struct MinutelyForecast {
+    pub time: DateTime<Utc>,
+    /// Total precipitation intensity in mm/hr.
+    pub precipitation_intensity: f64,
+    /// Dominant precipitation type, `None` when it is dry.
+    pub precipitation_type: Option<PrecipitationType>,
+    pub precipitation_probability: Option<f64>,
+}
@@ -0,0 +1,18 @@
+mod client;
+mod coordinate;
+mod forecast;
+mod open_meteo;
+mod provider;
+mod qweather;
+mod tomorrow_io;
+
+pub use client::{ForecastAttempts, ProviderForecast, ProviderObservation, WeatherClient};
+pub use coordinate::{Coordinate, CoordinateParseError, Datum};
+pub use forecast::{
+    DailyForecast, Forecast, HourlyForecast, MinutelyForecast, Observation, PrecipitationType,
+    STORED_UNITS,
+};
+pub use open_meteo::OpenMeteoProvider;
+pub use provider::{WeatherProvider, WeatherProviderKind};
+pub use qweather::{QWeatherAuth, QWeatherJwtSigner, QWeatherProvider, QWEATHER_FORECAST_HOURS};
+pub use tomorrow_io::TomorrowIoProvider;
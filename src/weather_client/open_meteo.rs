@@ -0,0 +1,249 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Response};
+use serde::{de::Error, Deserialize};
+use serde_json::Value;
+use tracing::info;
+
+use super::{
+    Coordinate, DailyForecast, Forecast, HourlyForecast, Observation, WeatherProvider,
+    WeatherProviderKind,
+};
+
+const SNOW_TO_WATER_RATIO: f64 = 7.0;
+const HOURLY_VARIABLES: &str = "temperature_2m,apparent_temperature,dew_point_2m,\
+relative_humidity_2m,precipitation_probability,rain,snowfall,wind_speed_10m,wind_direction_10m,\
+wind_gusts_10m,surface_pressure,uv_index,visibility,cloud_cover,weather_code";
+const DAILY_VARIABLES: &str =
+    "temperature_2m_min,temperature_2m_max,sunrise,sunset,precipitation_sum,weather_code";
+const CURRENT_VARIABLES: &str = "temperature_2m,apparent_temperature,dew_point_2m,\
+relative_humidity_2m,rain,snowfall,wind_speed_10m,wind_direction_10m,wind_gusts_10m,\
+surface_pressure,visibility,cloud_cover,weather_code";
+
+#[derive(Deserialize, Debug)]
+struct OpenMeteoResponse {
+    hourly: HourlyArrays,
+    daily: Option<DailyArrays>,
+}
+/// Variables missing from a response, e.g. archived before they were
+/// requested, deserialize as empty arrays.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct HourlyArrays {
+    time: Vec<i64>,
+    temperature_2m: Vec<Option<f64>>,
+    apparent_temperature: Vec<Option<f64>>,
+    dew_point_2m: Vec<Option<f64>>,
+    relative_humidity_2m: Vec<Option<f64>>,
+    precipitation_probability: Vec<Option<f64>>,
+    rain: Vec<Option<f64>>,
+    snowfall: Vec<Option<f64>>,
+    wind_speed_10m: Vec<Option<f64>>,
+    wind_direction_10m: Vec<Option<f64>>,
+    wind_gusts_10m: Vec<Option<f64>>,
+    surface_pressure: Vec<Option<f64>>,
+    uv_index: Vec<Option<f64>>,
+    visibility: Vec<Option<f64>>,
+    cloud_cover: Vec<Option<f64>>,
+    weather_code: Vec<Option<i32>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CurrentResponse {
+    current: CurrentValues,
+}
+#[derive(Deserialize, Debug)]
+struct CurrentValues {
+    time: i64,
+    temperature_2m: Option<f64>,
+    apparent_temperature: Option<f64>,
+    dew_point_2m: Option<f64>,
+    relative_humidity_2m: Option<f64>,
+    rain: Option<f64>,
+    snowfall: Option<f64>,
+    wind_speed_10m: Option<f64>,
+    wind_direction_10m: Option<f64>,
+    wind_gusts_10m: Option<f64>,
+    surface_pressure: Option<f64>,
+    visibility: Option<f64>,
+    cloud_cover: Option<f64>,
+    weather_code: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DailyArrays {
+    time: Vec<i64>,
+    temperature_2m_min: Vec<Option<f64>>,
+    temperature_2m_max: Vec<Option<f64>>,
+    sunrise: Vec<Option<i64>>,
+    sunset: Vec<Option<i64>>,
+    precipitation_sum: Vec<Option<f64>>,
+    weather_code: Vec<Option<i32>>,
+}
+
+/// Keyless provider backed by <https://open-meteo.com>.
+pub struct OpenMeteoProvider {
+    base_url: String,
+    http_client: Client,
+}
+
+impl OpenMeteoProvider {
+    pub fn new(base_url: String, timeout: std::time::Duration) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+        Self {
+            base_url,
+            http_client,
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenMeteoProvider {
+    fn kind(&self) -> WeatherProviderKind {
+        WeatherProviderKind::OpenMeteo
+    }
+
+    async fn fetch_forecast(&self, location: &Coordinate) -> Result<Response, reqwest::Error> {
+        let latitude = format!("{:.4}", location.latitude);
+        let longitude = format!("{:.4}", location.longitude);
+        let forecast_response = self
+            .http_client
+            .get(format!("{}/forecast", self.base_url))
+            .query(&[
+                ("latitude", latitude.as_str()),
+                ("longitude", longitude.as_str()),
+                ("hourly", HOURLY_VARIABLES),
+                ("daily", DAILY_VARIABLES),
+                ("wind_speed_unit", "ms"),
+                ("timeformat", "unixtime"),
+                // Daily values cover the local day of the coordinate, not the
+                // UTC one; unix timestamps keep hourly times absolute.
+                ("timezone", "auto"),
+            ])
+            .header("accept", "application/json")
+            .send()
+            .await?;
+        info!(
+            location = format!("{},{}", latitude, longitude),
+            status = forecast_response.status().as_u16(),
+            "Received forecast response",
+        );
+        Ok(forecast_response)
+    }
+
+    async fn fetch_observation(&self, location: &Coordinate) -> Result<Response, reqwest::Error> {
+        let latitude = format!("{:.4}", location.latitude);
+        let longitude = format!("{:.4}", location.longitude);
+        let current_response = self
+            .http_client
+            .get(format!("{}/forecast", self.base_url))
+            .query(&[
+                ("latitude", latitude.as_str()),
+                ("longitude", longitude.as_str()),
+                ("current", CURRENT_VARIABLES),
+                ("wind_speed_unit", "ms"),
+                ("timeformat", "unixtime"),
+                ("timezone", "GMT"),
+            ])
+            .header("accept", "application/json")
+            .send()
+            .await?;
+        info!(
+            location = format!("{},{}", latitude, longitude),
+            status = current_response.status().as_u16(),
+            "Received current conditions response",
+        );
+        Ok(current_response)
+    }
+}
+
+pub(super) fn parse_forecast(json_data: Value) -> Result<Forecast, serde_json::Error> {
+    let forecast_data: OpenMeteoResponse = serde_json::from_value(json_data)?;
+    let hourly_data = forecast_data.hourly;
+    let mut hourly = Vec::with_capacity(hourly_data.time.len());
+    for (index, timestamp) in hourly_data.time.iter().enumerate() {
+        let time = parse_timestamp(*timestamp)?;
+        let value = |values: &Vec<Option<f64>>| values.get(index).copied().flatten();
+        hourly.push(HourlyForecast {
+            time,
+            precipitation_probability: value(&hourly_data.precipitation_probability),
+            rain_intensity: value(&hourly_data.rain),
+            snow_intensity: value(&hourly_data.snowfall).map(snow_water_equivalent),
+            temperature: value(&hourly_data.temperature_2m),
+            temperature_apparent: value(&hourly_data.apparent_temperature),
+            dew_point: value(&hourly_data.dew_point_2m),
+            humidity: value(&hourly_data.relative_humidity_2m),
+            wind_speed: value(&hourly_data.wind_speed_10m),
+            wind_direction: value(&hourly_data.wind_direction_10m),
+            wind_gust: value(&hourly_data.wind_gusts_10m),
+            pressure_surface_level: value(&hourly_data.surface_pressure),
+            uv_index: value(&hourly_data.uv_index),
+            // Open-Meteo reports visibility in metres.
+            visibility: value(&hourly_data.visibility).map(|visibility| visibility / 1000.0),
+            cloud_cover: value(&hourly_data.cloud_cover),
+            weather_code: hourly_data.weather_code.get(index).copied().flatten(),
+            ..Default::default()
+        });
+    }
+    let mut daily = Vec::new();
+    if let Some(daily_data) = forecast_data.daily {
+        for (index, timestamp) in daily_data.time.iter().enumerate() {
+            let value = |values: &Vec<Option<f64>>| values.get(index).copied().flatten();
+            let time_value = |values: &Vec<Option<i64>>| {
+                values
+                    .get(index)
+                    .copied()
+                    .flatten()
+                    .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+            };
+            daily.push(DailyForecast {
+                time: parse_timestamp(*timestamp)?,
+                temperature_min: value(&daily_data.temperature_2m_min),
+                temperature_max: value(&daily_data.temperature_2m_max),
+                sunrise_time: time_value(&daily_data.sunrise),
+                sunset_time: time_value(&daily_data.sunset),
+                precipitation_accumulation: value(&daily_data.precipitation_sum),
+                weather_code: daily_data.weather_code.get(index).copied().flatten(),
+            });
+        }
+    }
+    Ok(Forecast {
+        hourly,
+        daily,
+        minutely: Vec::new(),
+    })
+}
+
+/// Open-Meteo's "current" block is model data for the latest 15 minute step,
+/// the closest it offers to an observation.
+pub(super) fn parse_observation(json_data: Value) -> Result<Observation, serde_json::Error> {
+    let current = serde_json::from_value::<CurrentResponse>(json_data)?.current;
+    Ok(Observation {
+        time: parse_timestamp(current.time)?,
+        rain_intensity: current.rain,
+        snow_intensity: current.snowfall.map(|snowfall| snowfall * 10.0),
+        temperature: current.temperature_2m,
+        temperature_apparent: current.apparent_temperature,
+        dew_point: current.dew_point_2m,
+        humidity: current.relative_humidity_2m,
+        wind_speed: current.wind_speed_10m,
+        wind_direction: current.wind_direction_10m,
+        wind_gust: current.wind_gusts_10m,
+        pressure_surface_level: current.surface_pressure,
+        visibility: current.visibility.map(|visibility| visibility / 1000.0),
+        cloud_cover: current.cloud_cover,
+        weather_code: current.weather_code,
+        ..Default::default()
+    })
+}
+
+fn parse_timestamp(timestamp: i64) -> Result<DateTime<Utc>, serde_json::Error> {
+    DateTime::from_timestamp(timestamp, 0)
+        .ok_or_else(|| serde_json::Error::custom(format!("invalid timestamp {}", timestamp)))
+}
+
+/// Open-Meteo reports snowfall as cm of fresh snow, tomorrow.io snow intensity
+/// as mm/hr of liquid water; 1 cm of snow holds about 1/7 cm of water.
+fn snow_water_equivalent(snowfall: f64) -> f64 {
+    snowfall * 10.0 / SNOW_TO_WATER_RATIO
+}
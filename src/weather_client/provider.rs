@@ -0,0 +1,76 @@
+use axum::async_trait;
+use reqwest::Response;
+use serde_json::Value;
+
+use super::{open_meteo, qweather, tomorrow_io, Coordinate, Forecast, Observation};
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherProviderKind {
+    TomorrowIo,
+    OpenMeteo,
+    #[serde(rename = "qweather")]
+    QWeather,
+}
+
+impl WeatherProviderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WeatherProviderKind::TomorrowIo => "tomorrow_io",
+            WeatherProviderKind::OpenMeteo => "open_meteo",
+            WeatherProviderKind::QWeather => "qweather",
+        }
+    }
+
+    /// Parsing needs no credentials, so stored responses can be re-parsed
+    /// even when the provider is no longer configured.
+    pub fn parse_forecast(&self, json_data: Value) -> Result<Forecast, serde_json::Error> {
+        match self {
+            WeatherProviderKind::TomorrowIo => tomorrow_io::parse_forecast(json_data),
+            WeatherProviderKind::OpenMeteo => open_meteo::parse_forecast(json_data),
+            WeatherProviderKind::QWeather => qweather::parse_forecast(json_data),
+        }
+    }
+
+    pub fn parse_observation(&self, json_data: Value) -> Result<Observation, serde_json::Error> {
+        match self {
+            WeatherProviderKind::TomorrowIo => tomorrow_io::parse_observation(json_data),
+            WeatherProviderKind::OpenMeteo => open_meteo::parse_observation(json_data),
+            WeatherProviderKind::QWeather => qweather::parse_observation(json_data),
+        }
+    }
+}
+
+impl TryFrom<String> for WeatherProviderKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "tomorrow_io" => Ok(Self::TomorrowIo),
+            "open_meteo" => Ok(Self::OpenMeteo),
+            "qweather" => Ok(Self::QWeather),
+            other => Err(format!("{} is not a supported weather provider.", other)),
+        }
+    }
+}
+
+/// A weather vendor. `fetch_forecast` returns the vendor's HTTP response,
+/// whatever its status, and `parse_forecast` maps its JSON into the
+/// provider-neutral `Forecast`. `fetch_observation` and `parse_observation` do
+/// the same for current conditions.
+#[async_trait]
+pub trait WeatherProvider: Send + Sync {
+    fn kind(&self) -> WeatherProviderKind;
+
+    async fn fetch_forecast(&self, location: &Coordinate) -> Result<Response, reqwest::Error>;
+
+    fn parse_forecast(&self, json_data: Value) -> Result<Forecast, serde_json::Error> {
+        self.kind().parse_forecast(json_data)
+    }
+
+    async fn fetch_observation(&self, location: &Coordinate) -> Result<Response, reqwest::Error>;
+
+    fn parse_observation(&self, json_data: Value) -> Result<Observation, serde_json::Error> {
+        self.kind().parse_observation(json_data)
+    }
+}
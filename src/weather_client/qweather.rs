@@ -0,0 +1,297 @@
+use axum::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{pkcs8::DecodePrivateKey, Signer, SigningKey};
+use reqwest::{Client, RequestBuilder, Response};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{de::Error, Deserialize};
+use serde_json::{json, Value};
+use tracing::info;
+
+use super::{
+    Coordinate, Forecast, HourlyForecast, Observation, WeatherProvider, WeatherProviderKind,
+};
+
+/// Hourly forecast lengths offered by the `/weather/{hours}h` endpoints.
+pub const QWEATHER_FORECAST_HOURS: [u16; 3] = [24, 72, 168];
+
+#[derive(Deserialize, Debug)]
+struct QWeatherResponse {
+    code: String,
+    #[serde(default)]
+    hourly: Vec<QWeatherHourly>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct QWeatherHourly {
+    fx_time: String,
+    temp: Option<String>,
+    icon: Option<String>,
+    wind360: Option<String>,
+    wind_speed: Option<String>,
+    humidity: Option<String>,
+    pop: Option<String>,
+    precip: Option<String>,
+    pressure: Option<String>,
+    cloud: Option<String>,
+    dew: Option<String>,
+}
+#[derive(Deserialize, Debug)]
+struct QWeatherNowResponse {
+    code: String,
+    now: Option<QWeatherNow>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct QWeatherNow {
+    obs_time: String,
+    temp: Option<String>,
+    feels_like: Option<String>,
+    icon: Option<String>,
+    wind360: Option<String>,
+    wind_speed: Option<String>,
+    humidity: Option<String>,
+    precip: Option<String>,
+    pressure: Option<String>,
+    vis: Option<String>,
+    cloud: Option<String>,
+    dew: Option<String>,
+}
+
+pub enum QWeatherAuth {
+    Key(SecretString),
+    Jwt(Box<QWeatherJwtSigner>),
+}
+
+/// Signs the short-lived EdDSA tokens QWeather accepts as `Authorization: Bearer`.
+pub struct QWeatherJwtSigner {
+    key_id: String,
+    project_id: String,
+    signing_key: SigningKey,
+}
+
+impl QWeatherJwtSigner {
+    pub fn new(
+        key_id: String,
+        project_id: String,
+        private_key_pem: &SecretString,
+    ) -> Result<Self, anyhow::Error> {
+        let signing_key = SigningKey::from_pkcs8_pem(private_key_pem.expose_secret())
+            .map_err(|e| anyhow::anyhow!("invalid QWeather Ed25519 private key: {}", e))?;
+        Ok(Self {
+            key_id,
+            project_id,
+            signing_key,
+        })
+    }
+
+    fn token(&self) -> String {
+        let issued_at = Utc::now().timestamp() - 30;
+        let header = json!({"alg": "EdDSA", "kid": self.key_id});
+        let payload = json!({"sub": self.project_id, "iat": issued_at, "exp": issued_at + 900});
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(payload.to_string())
+        );
+        let signature = self.signing_key.sign(signing_input.as_bytes());
+        format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        )
+    }
+}
+
+/// Provider backed by QWeather (和风天气), which covers mainland China far better
+/// than tomorrow.io.
+pub struct QWeatherProvider {
+    base_url: String,
+    http_client: Client,
+    auth: QWeatherAuth,
+    forecast_hours: u16,
+}
+
+impl QWeatherProvider {
+    pub fn new(
+        base_url: String,
+        auth: QWeatherAuth,
+        forecast_hours: u16,
+        timeout: std::time::Duration,
+    ) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+        Self {
+            base_url,
+            http_client,
+            auth,
+            forecast_hours,
+        }
+    }
+
+    fn get(&self, path: &str, location: &Coordinate) -> RequestBuilder {
+        // QWeather expects "longitude,latitude" with at most two decimals.
+        let location = format!("{:.2},{:.2}", location.longitude, location.latitude);
+        let request = self
+            .http_client
+            .get(format!("{}{}", self.base_url, path))
+            .query(&[("location", location.as_str())])
+            .header("accept", "application/json");
+        match &self.auth {
+            QWeatherAuth::Key(api_key) => request.query(&[("key", api_key.expose_secret())]),
+            QWeatherAuth::Jwt(signer) => request.bearer_auth(signer.token()),
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for QWeatherProvider {
+    fn kind(&self) -> WeatherProviderKind {
+        WeatherProviderKind::QWeather
+    }
+
+    async fn fetch_forecast(&self, location: &Coordinate) -> Result<Response, reqwest::Error> {
+        let forecast_response = self
+            .get(&format!("/weather/{}h", self.forecast_hours), location)
+            .send()
+            .await?;
+        info!(
+            location = format!("{:.2},{:.2}", location.longitude, location.latitude),
+            status = forecast_response.status().as_u16(),
+            "Received forecast response",
+        );
+        Ok(forecast_response)
+    }
+
+    async fn fetch_observation(&self, location: &Coordinate) -> Result<Response, reqwest::Error> {
+        let now_response = self.get("/weather/now", location).send().await?;
+        info!(
+            location = format!("{:.2},{:.2}", location.longitude, location.latitude),
+            status = now_response.status().as_u16(),
+            "Received current conditions response",
+        );
+        Ok(now_response)
+    }
+}
+
+pub(super) fn parse_forecast(json_data: Value) -> Result<Forecast, serde_json::Error> {
+    let forecast_data: QWeatherResponse = serde_json::from_value(json_data)?;
+    if forecast_data.code != "200" {
+        return Err(serde_json::Error::custom(format!(
+            "QWeather returned status code {}",
+            forecast_data.code
+        )));
+    }
+    let hourly = forecast_data
+        .hourly
+        .into_iter()
+        .map(|weather_data| {
+            let time = DateTime::parse_from_str(&weather_data.fx_time, "%Y-%m-%dT%H:%M%:z")
+                .map_err(serde_json::Error::custom)?
+                .with_timezone(&Utc);
+            let temperature = parse_number(&weather_data.temp)?;
+            let humidity = parse_number(&weather_data.humidity)?;
+            // QWeather reports km/h, the other providers m/s.
+            let wind_speed = parse_number(&weather_data.wind_speed)?.map(|speed| speed / 3.6);
+            let precip = parse_number(&weather_data.precip)?;
+            let icon = weather_data.icon.as_deref().unwrap_or_default();
+            let (rain_intensity, sleet_intensity, snow_intensity) =
+                split_precipitation(icon, precip);
+            let temperature_apparent = match (temperature, humidity, wind_speed) {
+                (Some(temperature), Some(humidity), Some(wind_speed)) => {
+                    Some(apparent_temperature(temperature, humidity, wind_speed))
+                }
+                _ => None,
+            };
+            Ok(HourlyForecast {
+                time,
+                precipitation_probability: parse_number(&weather_data.pop)?,
+                rain_intensity,
+                sleet_intensity,
+                snow_intensity,
+                temperature,
+                temperature_apparent,
+                dew_point: parse_number(&weather_data.dew)?,
+                humidity,
+                wind_speed,
+                wind_direction: parse_number(&weather_data.wind360)?,
+                pressure_surface_level: parse_number(&weather_data.pressure)?,
+                cloud_cover: parse_number(&weather_data.cloud)?,
+                weather_code: icon.parse().ok(),
+                ..Default::default()
+            })
+        })
+        .collect::<Result<Vec<_>, serde_json::Error>>()?;
+    // Daily forecasts live on the separate /weather/{days}d endpoints.
+    Ok(Forecast {
+        hourly,
+        daily: Vec::new(),
+        minutely: Vec::new(),
+    })
+}
+
+pub(super) fn parse_observation(json_data: Value) -> Result<Observation, serde_json::Error> {
+    let now_data: QWeatherNowResponse = serde_json::from_value(json_data)?;
+    let now = match (now_data.code.as_str(), now_data.now) {
+        ("200", Some(now)) => now,
+        (code, _) => {
+            return Err(serde_json::Error::custom(format!(
+                "QWeather returned status code {}",
+                code
+            )))
+        }
+    };
+    let time = DateTime::parse_from_str(&now.obs_time, "%Y-%m-%dT%H:%M%:z")
+        .map_err(serde_json::Error::custom)?
+        .with_timezone(&Utc);
+    let icon = now.icon.as_deref().unwrap_or_default();
+    // `precip` is the accumulation over the past hour, i.e. mm/hr.
+    let (rain_intensity, sleet_intensity, snow_intensity) =
+        split_precipitation(icon, parse_number(&now.precip)?);
+    Ok(Observation {
+        time,
+        rain_intensity,
+        sleet_intensity,
+        snow_intensity,
+        temperature: parse_number(&now.temp)?,
+        temperature_apparent: parse_number(&now.feels_like)?,
+        dew_point: parse_number(&now.dew)?,
+        humidity: parse_number(&now.humidity)?,
+        wind_speed: parse_number(&now.wind_speed)?.map(|speed| speed / 3.6),
+        wind_direction: parse_number(&now.wind360)?,
+        pressure_surface_level: parse_number(&now.pressure)?,
+        visibility: parse_number(&now.vis)?,
+        cloud_cover: parse_number(&now.cloud)?,
+        weather_code: icon.parse().ok(),
+        ..Default::default()
+    })
+}
+
+/// The icon code is the only hint at which kind of precipitation `precip` is,
+/// returned as (rain, sleet, snow).
+fn split_precipitation(icon: &str, precip: Option<f64>) -> (Option<f64>, Option<f64>, Option<f64>) {
+    match icon {
+        "404" | "405" | "406" => (Some(0.0), precip, Some(0.0)),
+        icon if icon.starts_with('4') => (Some(0.0), Some(0.0), precip),
+        icon if icon.starts_with('3') => (precip, Some(0.0), Some(0.0)),
+        _ => (Some(0.0), Some(0.0), Some(0.0)),
+    }
+}
+
+/// QWeather sends numbers as strings and leaves unavailable ones empty.
+fn parse_number(value: &Option<String>) -> Result<Option<f64>, serde_json::Error> {
+    match value.as_deref() {
+        None | Some("") => Ok(None),
+        Some(value) => value
+            .parse::<f64>()
+            .map(Some)
+            .map_err(|e| serde_json::Error::custom(format!("invalid number {:?}: {}", value, e))),
+    }
+}
+
+/// The hourly endpoints carry no feels-like value, so it is derived with the
+/// Australian Bureau of Meteorology apparent temperature formula.
+fn apparent_temperature(temperature: f64, humidity: f64, wind_speed: f64) -> f64 {
+    let vapour_pressure =
+        humidity / 100.0 * 6.105 * (17.27 * temperature / (237.7 + temperature)).exp();
+    temperature + 0.33 * vapour_pressure - 0.70 * wind_speed - 4.00
+}
@@ -0,0 +1,268 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Response};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::info;
+
+use super::{
+    Coordinate, DailyForecast, Forecast, HourlyForecast, MinutelyForecast, Observation,
+    PrecipitationType, WeatherProvider, WeatherProviderKind,
+};
+
+#[derive(Deserialize, Debug)]
+struct WeatherForecastResponse {
+    timelines: Timelines,
+}
+#[derive(Deserialize, Debug)]
+struct Timelines {
+    hourly: Vec<WeatherData>,
+    #[serde(default)]
+    daily: Vec<DailyWeatherData>,
+    #[serde(default)]
+    minutely: Vec<MinutelyWeatherData>,
+}
+#[derive(Deserialize, Debug)]
+struct RealtimeResponse {
+    data: WeatherData,
+}
+#[derive(Deserialize, Debug)]
+struct WeatherData {
+    time: DateTime<Utc>,
+    values: WeatherValues,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct WeatherValues {
+    precipitation_probability: Option<f64>,
+    rain_intensity: Option<f64>,
+    freezing_rain_intensity: Option<f64>,
+    sleet_intensity: Option<f64>,
+    snow_intensity: Option<f64>,
+    temperature: Option<f64>,
+    temperature_apparent: Option<f64>,
+    dew_point: Option<f64>,
+    humidity: Option<f64>,
+    wind_speed: Option<f64>,
+    wind_direction: Option<f64>,
+    wind_gust: Option<f64>,
+    pressure_surface_level: Option<f64>,
+    uv_index: Option<f64>,
+    visibility: Option<f64>,
+    cloud_cover: Option<f64>,
+    cloud_base: Option<f64>,
+    cloud_ceiling: Option<f64>,
+    weather_code: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DailyWeatherData {
+    time: DateTime<Utc>,
+    values: DailyWeatherValues,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct DailyWeatherValues {
+    temperature_min: Option<f64>,
+    temperature_max: Option<f64>,
+    sunrise_time: Option<DateTime<Utc>>,
+    sunset_time: Option<DateTime<Utc>>,
+    rain_accumulation_sum: Option<f64>,
+    sleet_accumulation_lwe_sum: Option<f64>,
+    snow_accumulation_lwe_sum: Option<f64>,
+    weather_code_max: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MinutelyWeatherData {
+    time: DateTime<Utc>,
+    values: MinutelyWeatherValues,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct MinutelyWeatherValues {
+    precipitation_probability: Option<f64>,
+    #[serde(default)]
+    rain_intensity: f64,
+    #[serde(default)]
+    snow_intensity: f64,
+    #[serde(default)]
+    sleet_intensity: f64,
+    #[serde(default)]
+    freezing_rain_intensity: f64,
+}
+
+pub struct TomorrowIoProvider {
+    base_url: String,
+    http_client: Client,
+    authorization_token: SecretString,
+}
+
+impl TomorrowIoProvider {
+    pub fn new(
+        base_url: String,
+        authorization_token: SecretString,
+        timeout: std::time::Duration,
+    ) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+        Self {
+            base_url,
+            http_client,
+            authorization_token,
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for TomorrowIoProvider {
+    fn kind(&self) -> WeatherProviderKind {
+        WeatherProviderKind::TomorrowIo
+    }
+
+    async fn fetch_forecast(&self, location: &Coordinate) -> Result<Response, reqwest::Error> {
+        let location = format!("{:.4},{:.4}", location.latitude, location.longitude);
+        let url = format!(
+            "{}/forecast?location={}&units=metric&apikey={}",
+            self.base_url,
+            location,
+            self.authorization_token.expose_secret()
+        );
+        let forecast_response = self
+            .http_client
+            .get(&url)
+            .header("accept", "application/json")
+            .send()
+            .await?;
+        info!(
+            location = &location,
+            status = forecast_response.status().as_u16(),
+            "Received forecast response",
+        );
+        Ok(forecast_response)
+    }
+
+    async fn fetch_observation(&self, location: &Coordinate) -> Result<Response, reqwest::Error> {
+        let location = format!("{:.4},{:.4}", location.latitude, location.longitude);
+        let url = format!(
+            "{}/realtime?location={}&units=metric&apikey={}",
+            self.base_url,
+            location,
+            self.authorization_token.expose_secret()
+        );
+        let realtime_response = self
+            .http_client
+            .get(&url)
+            .header("accept", "application/json")
+            .send()
+            .await?;
+        info!(
+            location = &location,
+            status = realtime_response.status().as_u16(),
+            "Received realtime response",
+        );
+        Ok(realtime_response)
+    }
+}
+
+pub(super) fn parse_forecast(json_data: Value) -> Result<Forecast, serde_json::Error> {
+    let forecast_data: WeatherForecastResponse = serde_json::from_value(json_data)?;
+    let hourly = forecast_data
+        .timelines
+        .hourly
+        .into_iter()
+        .map(hourly_forecast)
+        .collect();
+    let daily = forecast_data
+        .timelines
+        .daily
+        .into_iter()
+        .map(|weather_data| {
+            let values = weather_data.values;
+            let precipitation_accumulation = match (
+                values.rain_accumulation_sum,
+                values.sleet_accumulation_lwe_sum,
+                values.snow_accumulation_lwe_sum,
+            ) {
+                (None, None, None) => None,
+                (rain, sleet, snow) => {
+                    Some(rain.unwrap_or(0.0) + sleet.unwrap_or(0.0) + snow.unwrap_or(0.0))
+                }
+            };
+            DailyForecast {
+                time: weather_data.time,
+                temperature_min: values.temperature_min,
+                temperature_max: values.temperature_max,
+                sunrise_time: values.sunrise_time,
+                sunset_time: values.sunset_time,
+                precipitation_accumulation,
+                weather_code: values.weather_code_max,
+            }
+        })
+        .collect();
+    let minutely = forecast_data
+        .timelines
+        .minutely
+        .into_iter()
+        .map(|weather_data| {
+            let values = weather_data.values;
+            let intensities = [
+                (PrecipitationType::Rain, values.rain_intensity),
+                (PrecipitationType::Snow, values.snow_intensity),
+                (PrecipitationType::Sleet, values.sleet_intensity),
+                (
+                    PrecipitationType::FreezingRain,
+                    values.freezing_rain_intensity,
+                ),
+            ];
+            let precipitation_type = intensities
+                .iter()
+                .filter(|(_, intensity)| *intensity > 0.0)
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(precipitation_type, _)| *precipitation_type);
+            MinutelyForecast {
+                time: weather_data.time,
+                precipitation_intensity: intensities.iter().map(|(_, intensity)| intensity).sum(),
+                precipitation_type,
+                precipitation_probability: values.precipitation_probability,
+            }
+        })
+        .collect();
+    Ok(Forecast {
+        hourly,
+        daily,
+        minutely,
+    })
+}
+
+pub(super) fn parse_observation(json_data: Value) -> Result<Observation, serde_json::Error> {
+    let realtime_data: RealtimeResponse = serde_json::from_value(json_data)?;
+    Ok(hourly_forecast(realtime_data.data))
+}
+
+/// The hourly timeline and `/realtime` share the same value names.
+fn hourly_forecast(weather_data: WeatherData) -> HourlyForecast {
+    let values = weather_data.values;
+    HourlyForecast {
+        time: weather_data.time,
+        precipitation_probability: values.precipitation_probability,
+        rain_intensity: values.rain_intensity,
+        freezing_rain_intensity: values.freezing_rain_intensity,
+        sleet_intensity: values.sleet_intensity,
+        snow_intensity: values.snow_intensity,
+        temperature: values.temperature,
+        temperature_apparent: values.temperature_apparent,
+        dew_point: values.dew_point,
+        humidity: values.humidity,
+        wind_speed: values.wind_speed,
+        wind_direction: values.wind_direction,
+        wind_gust: values.wind_gust,
+        pressure_surface_level: values.pressure_surface_level,
+        uv_index: values.uv_index,
+        visibility: values.visibility,
+        cloud_cover: values.cloud_cover,
+        cloud_base: values.cloud_base,
+        cloud_ceiling: values.cloud_ceiling,
+        weather_code: values.weather_code,
+    }
+}
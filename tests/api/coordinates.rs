@@ -0,0 +1,150 @@
+use serde_json::{json, Value};
+use weather_forecast_wechat_bot::weather_client::{Coordinate, Datum};
+
+use crate::helper::{spawn_app, TestApp};
+
+async fn create_location(app: &TestApp, location: &str) -> (u16, Value) {
+    let response = app
+        .api_client
+        .post(format!("{}/api/v1/locations", app.address))
+        .bearer_auth(&app.test_user.token)
+        .json(&json!({"name": "Pasted", "location": location}))
+        .send()
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap())
+}
+
+fn assert_near(location: &Value, latitude: f64, longitude: f64) {
+    let actual = (
+        location["latitude"].as_f64().unwrap(),
+        location["longitude"].as_f64().unwrap(),
+    );
+    assert!(
+        (actual.0 - latitude).abs() < 1e-4 && (actual.1 - longitude).abs() < 1e-4,
+        "expected ({}, {}), got {:?}",
+        latitude,
+        longitude,
+        actual
+    );
+}
+
+#[tokio::test]
+async fn locations_accept_dms_geo_uri_plus_code_and_geohash() {
+    let app = spawn_app().await;
+    let cases = [
+        ("39°54'N 116°23'E", 39.9, 116.383333),
+        ("39°54′26″N, 116°23′29″E", 39.907222, 116.391389),
+        ("北纬39°54′ 东经116°23′", 39.9, 116.383333),
+        ("33°52'S 151°12'E", -33.866667, 151.2),
+        ("E116.4 N39.9", 39.9, 116.4),
+        ("geo:39.9,116.4", 39.9, 116.4),
+        ("geo:-33.87,151.21,12;u=35?q=Sydney", -33.87, 151.21),
+        ("8FVC9G8F+6X", 47.3655625, 8.5249375),
+        ("7FG49Q00+", 20.375, 2.775),
+        ("ezs42", 42.604980, -5.603027),
+        ("geohash:wx4g", 39.990234375, 116.54296875),
+    ];
+    for (location, latitude, longitude) in cases {
+        let (status, created) = create_location(&app, location).await;
+        assert_eq!(status, 201, "{}: {}", location, created);
+        assert_near(&created, latitude, longitude);
+    }
+}
+
+#[tokio::test]
+async fn locations_report_which_coordinate_format_is_invalid() {
+    let app = spawn_app().await;
+    let cases = [
+        (
+            "39°75'N 116°23'E",
+            "Invalid degrees-minutes-seconds coordinate",
+        ),
+        (
+            "39°54'N 116°23'N",
+            "Invalid degrees-minutes-seconds coordinate",
+        ),
+        ("geo:39.9", "Invalid geo URI"),
+        ("geo:39.9,116.4;crs=etrs89", "Invalid geo URI"),
+        (
+            "9G8F+6X",
+            "Invalid Plus Code: short codes need a reference location",
+        ),
+        ("8FVC9G8A+6X", "Invalid Plus Code"),
+        ("geohash:wx4gai", "Invalid geohash"),
+    ];
+    for (location, message) in cases {
+        let (status, body) = create_location(&app, location).await;
+        assert_eq!(status, 400, "{}: {}", location, body);
+        assert_eq!(body["status"], "VALIDATION_ERROR");
+        assert!(
+            body["content"].as_str().unwrap().contains(message),
+            "{}: {}",
+            location,
+            body
+        );
+    }
+}
+
+#[test]
+fn gcj02_and_bd09_convert_to_and_from_wgs84() {
+    let tiananmen = Coordinate::wgs84(39.9087, 116.3975);
+    let gcj02 = tiananmen.to_datum(Datum::Gcj02);
+    assert_eq!(gcj02.datum, Datum::Gcj02);
+    // GCJ-02 shifts Beijing by a few hundred meters, mostly eastward.
+    assert!((0.001..0.002).contains(&(gcj02.latitude - tiananmen.latitude)));
+    assert!((0.005..0.007).contains(&(gcj02.longitude - tiananmen.longitude)));
+    let bd09 = tiananmen.to_datum(Datum::Bd09);
+    assert!((0.006..0.009).contains(&(bd09.latitude - gcj02.latitude)));
+    assert!((0.006..0.007).contains(&(bd09.longitude - gcj02.longitude)));
+
+    for converted in [gcj02, bd09] {
+        let back = converted.to_wgs84();
+        assert_eq!(back.datum, Datum::Wgs84);
+        assert!((back.latitude - tiananmen.latitude).abs() < 1e-6);
+        assert!((back.longitude - tiananmen.longitude).abs() < 1e-6);
+    }
+
+    let zurich = Coordinate::wgs84(47.3656, 8.5249);
+    assert_eq!(zurich.to_datum(Datum::Gcj02).latitude, zurich.latitude);
+    assert_eq!(zurich.to_datum(Datum::Gcj02).longitude, zurich.longitude);
+}
+
+#[tokio::test]
+async fn locations_store_wgs84_and_record_the_submitted_datum() {
+    let app = spawn_app().await;
+    let expected = Coordinate::wgs84(39.9087, 116.3975);
+    let gcj02 = expected.to_datum(Datum::Gcj02);
+    let bd09 = expected.to_datum(Datum::Bd09);
+
+    let response = app
+        .api_client
+        .post(format!("{}/api/v1/locations", app.address))
+        .bearer_auth(&app.test_user.token)
+        .json(&json!({
+            "name": "WeChat share",
+            "location": format!("{},{}", gcj02.latitude, gcj02.longitude),
+            "datum": "gcj02"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    let created: Value = response.json().await.unwrap();
+    assert_near(&created, expected.latitude, expected.longitude);
+    assert_eq!(created["source_datum"], "gcj02");
+
+    let (status, created) = create_location(
+        &app,
+        &format!("geo:{},{};crs=bd09", bd09.latitude + 0.01, bd09.longitude),
+    )
+    .await;
+    assert_eq!(status, 201);
+    assert_near(&created, expected.latitude + 0.01, expected.longitude);
+    assert_eq!(created["source_datum"], "bd09");
+
+    let (_, created) = create_location(&app, "39.95,116.3975").await;
+    assert_near(&created, 39.95, 116.3975);
+    assert_eq!(created["source_datum"], "wgs84");
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+use wiremock::{matchers::path, Mock, ResponseTemplate};
+
+use crate::{
+    helper::{spawn_app, TestApp},
+    update_weather::tomorrow_io_forecast,
+};
+
+async fn nearest_as_of(app: &TestApp, as_of: DateTime<Utc>) -> Value {
+    app.api_client
+        .get(format!("{}/api/v1/forecasts/nearest", app.address))
+        .query(&[
+            ("lat", "39.9042"),
+            ("lon", "116.4074"),
+            ("as_of", &as_of.to_rfc3339_opts(SecondsFormat::Micros, true)),
+        ])
+        .bearer_auth(&app.test_user.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn update_weather_keeps_every_issued_forecast() {
+    let app = spawn_app().await;
+    let mut revised = tomorrow_io_forecast();
+    revised["timelines"]["hourly"][0]["values"]["temperature"] = json!(14.0);
+    revised["timelines"]["daily"][0]["values"]["temperatureMax"] = json!(16.0);
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.weather_server)
+        .await;
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(revised))
+        .expect(1)
+        .mount(&app.weather_server)
+        .await;
+    let body = json!({
+        "token": app.test_user.token,
+        "location": "39.9042, 116.4074",
+        "city_name": "Beijing"
+    });
+
+    app.post_update_weather(&body).await;
+    let between_issues = Utc::now();
+    app.post_update_weather(&body).await;
+
+    let stored = sqlx::query_scalar!("SELECT COUNT(*) FROM weather_info")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(stored, Some(4));
+    let earlier = nearest_as_of(&app, between_issues).await;
+    let earlier = earlier["data"].as_array().unwrap();
+    assert_eq!(earlier.len(), 2);
+    assert_eq!(earlier[0]["temperature"], 12.5);
+    let latest = nearest_as_of(&app, Utc::now()).await;
+    let latest = latest["data"].as_array().unwrap();
+    assert_eq!(latest.len(), 2);
+    assert_eq!(latest[0]["temperature"], 14.0);
+    assert_eq!(latest[0]["provider"], "tomorrow_io");
+    let daily = sqlx::query_scalar!(
+        "SELECT temperature_max FROM weather_daily ORDER BY forecast_issued_at"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(daily, vec![Some(15.0), Some(16.0)]);
+}
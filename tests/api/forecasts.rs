@@ -0,0 +1,574 @@
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{path, query_param},
+    Mock, ResponseTemplate,
+};
+
+use crate::{
+    helper::{spawn_app, TestApp},
+    update_weather::tomorrow_io_forecast,
+};
+
+pub async fn get_forecasts(app: &TestApp, query: &str) -> (u16, Value) {
+    let response = app
+        .api_client
+        .get(format!("{}/api/v1/forecasts?{}", app.address, query))
+        .bearer_auth(&app.test_user.token)
+        .send()
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap())
+}
+
+pub async fn store_beijing_forecast(app: &TestApp) -> String {
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .mount(&app.weather_server)
+        .await;
+    app.post_update_weather(&json!({
+        "token": app.test_user.token,
+        "location": "39.9042, 116.4074",
+        "city_name": "Beijing"
+    }))
+    .await;
+    sqlx::query_scalar!("SELECT id FROM locations")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn forecasts_are_read_back_by_location_and_time_range() {
+    let app = spawn_app().await;
+    let location_id = store_beijing_forecast(&app).await;
+
+    let (status, body) = get_forecasts(&app, &format!("location_id={}", location_id)).await;
+    assert_eq!(status, 200, "{}", body);
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0]["forecast_time"], "2024-11-01T00:00:00Z");
+    assert_eq!(data[0]["temperature"], 12.5);
+    assert_eq!(data[0]["provider"], "tomorrow_io");
+    assert_eq!(data[0]["location_id"], location_id.as_str());
+    assert_eq!(body["pagination"]["total"], 2);
+
+    let (_, body) = get_forecasts(
+        &app,
+        "lat=39.9042&lon=116.4074&from=2024-11-01T01:00&to=2024-11-01T02:00",
+    )
+    .await;
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["forecast_time"], "2024-11-01T01:00:00Z");
+
+    let (_, body) = get_forecasts(&app, "from=2024-11-02T00:00").await;
+    assert_eq!(body["data"], json!([]));
+}
+
+#[tokio::test]
+async fn forecasts_are_paginated_and_limited_to_selected_fields() {
+    let app = spawn_app().await;
+    store_beijing_forecast(&app).await;
+
+    let (_, body) = get_forecasts(&app, "fields=temperature,humidity&limit=1").await;
+    let row = body["data"][0].as_object().unwrap();
+    assert_eq!(row["temperature"], 12.5);
+    assert_eq!(row["humidity"], 45.0);
+    assert!(row.contains_key("forecast_time"));
+    assert!(!row.contains_key("wind_speed"));
+    assert_eq!(
+        body["pagination"],
+        json!({"limit": 1, "offset": 0, "total": 2, "next_offset": 1})
+    );
+
+    let (_, body) = get_forecasts(&app, "limit=1&offset=1").await;
+    assert_eq!(body["data"][0]["forecast_time"], "2024-11-01T01:00:00Z");
+    assert_eq!(body["pagination"]["next_offset"], Value::Null);
+}
+
+#[tokio::test]
+async fn forecasts_require_a_token_and_valid_parameters() {
+    let app = spawn_app().await;
+    let forecasts_url = format!("{}/api/v1/forecasts", app.address);
+
+    let response = app.api_client.get(&forecasts_url).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .api_client
+        .get(&forecasts_url)
+        .bearer_auth("not-a-token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    for query in [
+        "fields=temperature,sunshine",
+        "limit=0",
+        "lat=39.9",
+        "from=yesterday",
+        "location_id=not-a-uuid",
+    ] {
+        let (status, body) = get_forecasts(&app, query).await;
+        assert_eq!(status, 400, "{}: {}", query, body);
+        assert_eq!(body["status"], "VALIDATION_ERROR");
+    }
+    let (status, _) = get_forecasts(&app, "location_id=00000000-0000-0000-0000-000000000000").await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn nearest_forecast_finds_a_location_stored_close_by() {
+    let app = spawn_app().await;
+    let location_id = store_beijing_forecast(&app).await;
+
+    let (status, body) = get_forecasts(&app, "").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["pagination"]["total"], 2);
+    let response = app
+        .api_client
+        .get(format!(
+            "{}/api/v1/forecasts/nearest?lat=39.9041&lon=116.4075&fields=temperature",
+            app.address
+        ))
+        .bearer_auth(&app.test_user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["location"]["id"], location_id.as_str());
+    assert!(body["distance_km"].as_f64().unwrap() < 0.05);
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[1]["temperature"], 12.0);
+    assert!(!data[1].as_object().unwrap().contains_key("humidity"));
+
+    for (query, status) in [
+        ("lat=31.2304&lon=121.4737", 404),
+        ("lat=39.9041&lon=116.4075&radius_km=500", 400),
+        ("lat=39.9041", 400),
+    ] {
+        let response = app
+            .api_client
+            .get(format!(
+                "{}/api/v1/forecasts/nearest?{}",
+                app.address, query
+            ))
+            .bearer_auth(&app.test_user.token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), status, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn nearest_forecast_looks_across_the_antimeridian() {
+    let app = spawn_app().await;
+    let response = app
+        .api_client
+        .post(format!("{}/api/v1/locations", app.address))
+        .bearer_auth(&app.test_user.token)
+        .json(&json!({"name": "Taveuni", "location": "-16.8, 179.99"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .api_client
+        .get(format!(
+            "{}/api/v1/forecasts/nearest?lat=-16.8&lon=-179.99",
+            app.address
+        ))
+        .bearer_auth(&app.test_user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["location"]["name"], "Taveuni");
+    assert!(body["distance_km"].as_f64().unwrap() < 3.0);
+}
+
+#[tokio::test]
+async fn coordinates_are_snapped_on_write() {
+    let app = spawn_app().await;
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .expect(2)
+        .mount(&app.weather_server)
+        .await;
+
+    for location in ["39.90421, 116.40739", "39.904248,116.407412"] {
+        app.post_update_weather(&json!({
+            "token": app.test_user.token,
+            "location": location,
+            "city_name": "Beijing"
+        }))
+        .await;
+    }
+    let locations = sqlx::query!("SELECT latitude, longitude FROM locations")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(locations.len(), 1);
+    assert_eq!(
+        (locations[0].latitude, locations[0].longitude),
+        (39.9042, 116.4074)
+    );
+    let (_, body) = get_forecasts(&app, "lat=39.90422&lon=116.40738").await;
+    assert_eq!(body["pagination"]["total"], 4);
+}
+
+async fn export(app: &TestApp, query: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!("{}/api/v1/forecasts/export?{}", app.address, query))
+        .bearer_auth(&app.test_user.token)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn forecasts_export_as_csv_with_selected_columns_and_units() {
+    let app = spawn_app().await;
+    store_beijing_forecast(&app).await;
+
+    let response = export(
+        &app,
+        "format=csv&columns=forecast_time,temperature,wind_speed,city_name&units=imperial",
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/csv; charset=utf-8"
+    );
+    let csv = response.text().await.unwrap();
+    let lines: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+    assert_eq!(
+        lines[0],
+        ["forecast_time", "temperature", "wind_speed", "city_name"]
+    );
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[1][0], "2024-11-01T00:00:00Z");
+    let fahrenheit: f64 = lines[1][1].parse().unwrap();
+    let mph: f64 = lines[1][2].parse().unwrap();
+    assert!((fahrenheit - 54.5).abs() < 1e-9);
+    assert!((mph - 7.158).abs() < 1e-3);
+    assert_eq!(lines[1][3], "Beijing");
+}
+
+#[tokio::test]
+async fn forecasts_export_neutralizes_formulas_in_csv() {
+    let app = spawn_app().await;
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .mount(&app.weather_server)
+        .await;
+    app.post_update_weather(&json!({
+        "token": app.test_user.token,
+        "location": "-33.8688, 151.2093",
+        "city_name": "@SUM(A1:A9)"
+    }))
+    .await;
+
+    let response = export(&app, "columns=latitude,city_name").await;
+    let csv = response.text().await.unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[1], "-33.8688,'@SUM(A1:A9)");
+}
+
+#[tokio::test]
+async fn forecasts_export_as_ndjson() {
+    let app = spawn_app().await;
+    let location_id = store_beijing_forecast(&app).await;
+
+    let response = export(
+        &app,
+        &format!(
+            "format=ndjson&location_id={}&from=2024-11-01T01:00&columns=forecast_time,temperature&units=si",
+            location_id
+        ),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    let body = response.text().await.unwrap();
+    let rows: Vec<Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["forecast_time"], "2024-11-01T01:00:00Z");
+    assert!((rows[0]["temperature"].as_f64().unwrap() - 285.15).abs() < 1e-9);
+    assert_eq!(rows[0].as_object().unwrap().len(), 2);
+
+    for query in ["format=xml", "columns=temperature,sunshine", "units=kelvin"] {
+        let response = export(&app, query).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", query);
+    }
+}
+
+async fn get_geojson(app: &TestApp, query: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!(
+            "{}/api/v1/forecasts.geojson?{}",
+            app.address, query
+        ))
+        .bearer_auth(&app.test_user.token)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn forecasts_geojson_has_a_point_feature_per_location() {
+    let app = spawn_app().await;
+    let location_id = store_beijing_forecast(&app).await;
+
+    let response = get_geojson(&app, "time=2024-11-01T01:30&fields=temperature").await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "application/geo+json");
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["type"], "FeatureCollection");
+    let features = body["features"].as_array().unwrap();
+    assert_eq!(features.len(), 1);
+    let feature = &features[0];
+    assert_eq!(feature["id"], location_id.as_str());
+    assert_eq!(
+        feature["geometry"],
+        json!({"type": "Point", "coordinates": [116.4074, 39.9042]})
+    );
+    let properties = &feature["properties"];
+    assert_eq!(properties["forecast_time"], "2024-11-01T01:00:00Z");
+    assert_eq!(properties["temperature"], 12.0);
+    assert!(!properties.as_object().unwrap().contains_key("humidity"));
+
+    // Locations without a forecast for the hour still appear on the map.
+    let body: Value = get_geojson(&app, "time=2024-11-02T00:00")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let properties = &body["features"][0]["properties"];
+    assert_eq!(properties["location_id"], location_id.as_str());
+    assert_eq!(properties["forecast_time"], Value::Null);
+
+    for query in ["time=yesterday", "fields=sunshine"] {
+        let response = get_geojson(&app, query).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn forecast_calendar_has_an_all_day_event_per_local_day() {
+    let app = spawn_app().await;
+    let location_id = store_beijing_forecast(&app).await;
+    // Move the stored hours, under a clear sky, to today in Beijing, 08:00 and
+    // 09:00 local time, and the stored day to 06:00 today.
+    for query in [
+        r#"
+        UPDATE weather_info SET cloud_cover = 10.0, forecast_time = forecast_time
+            + (((now() AT TIME ZONE 'Asia/Shanghai')::DATE)::TIMESTAMP - TIMESTAMP '2024-11-01')
+        "#,
+        r#"
+        UPDATE weather_daily SET forecast_time = forecast_time
+            + (((now() AT TIME ZONE 'Asia/Shanghai')::DATE)::TIMESTAMP - TIMESTAMP '2024-11-01')
+        "#,
+    ] {
+        sqlx::query(query).execute(&app.db_pool).await.unwrap();
+    }
+    sqlx::query!("UPDATE locations SET timezone = 'Asia/Shanghai'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let today = sqlx::query_scalar!(
+        r#"SELECT to_char(now() AT TIME ZONE 'Asia/Shanghai', 'YYYYMMDD') AS "today!""#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+
+    let calendar_url =
+        |token: &str, file: &str| format!("{}/api/v1/calendar/{}/{}", app.address, token, file);
+    let event_summary = || async {
+        let body = app
+            .api_client
+            .get(calendar_url(
+                &app.test_user.token,
+                &format!("{}.ics", location_id),
+            ))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let summaries: Vec<String> = body
+            .split("\r\n")
+            .filter(|line| line.starts_with("SUMMARY:"))
+            .map(str::to_string)
+            .collect();
+        assert_eq!(summaries.len(), 1, "{}", body);
+        summaries[0].clone()
+    };
+    let response = app
+        .api_client
+        .get(calendar_url(
+            &app.test_user.token,
+            &format!("{}.ics", location_id),
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/calendar; charset=utf-8"
+    );
+    let body = response.text().await.unwrap();
+    let lines: Vec<&str> = body.split("\r\n").collect();
+    assert_eq!(lines[0], "BEGIN:VCALENDAR");
+    assert!(lines.contains(&"X-WR-TIMEZONE:Asia/Shanghai"));
+    assert_eq!(
+        lines.iter().filter(|line| **line == "BEGIN:VEVENT").count(),
+        1
+    );
+    assert!(lines.contains(&format!("DTSTART;VALUE=DATE:{}", today).as_str()));
+    // Temperatures and rain come from the daily forecast, the chance of
+    // rain from the hourly one.
+    assert!(lines.contains(&"SUMMARY:🌧 6–15°C\\, 20% 降水"), "{}", body);
+
+    // The newest issue of the day wins.
+    sqlx::query!(
+        r#"
+        INSERT INTO weather_daily
+            (id, user_id, latitude, longitude, city_name, provider, temperature_min, temperature_max,
+             precipitation_accumulation, forecast_time, forecast_issued_at)
+            SELECT gen_random_uuid(), user_id, latitude, longitude, city_name, provider,
+                temperature_min, 18.0, 0.0, forecast_time, forecast_issued_at + INTERVAL '1 hour'
+            FROM weather_daily
+        "#
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(event_summary().await, "SUMMARY:☀ 6–18°C\\, 20% 降水");
+
+    // Daily rows are converted from the unit system they record.
+    sqlx::query!(
+        "UPDATE weather_daily SET units = 'imperial', temperature_min = 42.8, temperature_max = 64.4 WHERE temperature_max = 18.0"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(event_summary().await, "SUMMARY:☀ 6–18°C\\, 20% 降水");
+    let body = app
+        .api_client
+        .get(format!(
+            "{}?units=imperial",
+            calendar_url(&app.test_user.token, &format!("{}.ics", location_id))
+        ))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(body.contains("SUMMARY:☀ 43–64°F\\, 20% 降水"), "{}", body);
+
+    // Without daily forecasts, the hourly ones are aggregated into days.
+    sqlx::query!("DELETE FROM weather_daily")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let summary = event_summary().await;
+    assert!(summary.starts_with("SUMMARY:☀ 12–12°C"), "{}", summary);
+    assert!(summary.ends_with("\\, 20% 降水"), "{}", summary);
+
+    for (token, file, status) in [
+        ("not-a-token", format!("{}.ics", location_id), 401),
+        (
+            app.test_user.token.as_str(),
+            format!("{}.ics", uuid::Uuid::new_v4()),
+            404,
+        ),
+        (
+            app.test_user.token.as_str(),
+            "calendar.ics".to_string(),
+            404,
+        ),
+    ] {
+        let response = app
+            .api_client
+            .get(calendar_url(token, &file))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), status, "{}", file);
+    }
+}
+
+#[tokio::test]
+async fn forecasts_convert_to_the_requested_units() {
+    let app = spawn_app().await;
+    Mock::given(path("/forecast"))
+        .and(query_param("units", "metric"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .mount(&app.weather_server)
+        .await;
+    let location_id = store_beijing_forecast(&app).await;
+    let units = sqlx::query_scalar!("SELECT DISTINCT units FROM weather_info")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(units, ["metric"]);
+
+    let (_, body) = get_forecasts(&app, &format!("location_id={}", location_id)).await;
+    assert_eq!(body["units"]["system"], "metric");
+    assert_eq!(body["units"]["wind_speed"], "m/s");
+    assert_eq!(body["data"][0]["wind_speed"], 3.2);
+
+    let (status, body) = get_forecasts(
+        &app,
+        &format!(
+            "location_id={}&units=imperial&wind_speed_unit=kmh",
+            location_id
+        ),
+    )
+    .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["units"]["temperature"], "°F");
+    assert_eq!(body["units"]["wind_speed"], "km/h");
+    let row = &body["data"][0];
+    assert!((row["temperature"].as_f64().unwrap() - 54.5).abs() < 1e-9);
+    assert!((row["wind_speed"].as_f64().unwrap() - 11.52).abs() < 1e-9);
+
+    // 3.2 m/s is a light breeze, Beaufort force 2.
+    let response = get_geojson(&app, "time=2024-11-01T00:00&wind_speed_unit=beaufort").await;
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["units"]["wind_speed"], "Bft");
+    assert_eq!(body["features"][0]["properties"]["wind_speed"], 2.0);
+
+    for query in ["units=kelvin", "wind_speed_unit=knots"] {
+        let (status, _) = get_forecasts(&app, query).await;
+        assert_eq!(status, 400, "{}", query);
+    }
+
+    // Rows are converted from the unit system they record.
+    sqlx::query!(
+        "UPDATE weather_info SET units = 'imperial', temperature = 54.5, wind_speed = 10.0 WHERE forecast_time = '2024-11-01 00:00'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let (_, body) = get_forecasts(&app, &format!("location_id={}", location_id)).await;
+    let row = &body["data"][0];
+    assert!((row["temperature"].as_f64().unwrap() - 12.5).abs() < 1e-9);
+    assert!((row["wind_speed"].as_f64().unwrap() - 4.4704).abs() < 1e-9);
+}
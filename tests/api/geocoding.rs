@@ -0,0 +1,180 @@
+use std::path::Path;
+
+use serde_json::{json, Value};
+use weather_forecast_wechat_bot::geocoding::{load_admin1_codes, load_gazetteer};
+use wiremock::{matchers::path, Mock, ResponseTemplate};
+
+use crate::{
+    helper::{spawn_app, TestApp},
+    update_weather::tomorrow_io_forecast,
+};
+
+async fn spawn_app_with_gazetteer() -> TestApp {
+    let app = spawn_app().await;
+    let loaded = load_gazetteer(
+        Path::new("tests/fixtures/gazetteer_cities.txt"),
+        &app.db_pool,
+    )
+    .await
+    .expect("Failed to load gazetteer.");
+    assert_eq!(loaded, 5);
+    load_admin1_codes(
+        Path::new("tests/fixtures/gazetteer_admin1.txt"),
+        &app.db_pool,
+    )
+    .await
+    .expect("Failed to load admin1 codes.");
+    app
+}
+
+async fn geocode(app: &TestApp, query: &str) -> Value {
+    app.api_client
+        .get(format!("{}/api/v1/geocode", app.address))
+        .query(&[("q", query)])
+        .bearer_auth(&app.test_user.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn geocode_matches_chinese_names_pinyin_and_suffixes() {
+    let app = spawn_app_with_gazetteer().await;
+
+    assert_eq!(geocode(&app, "北京市").await[0]["geoname_id"], 1816670);
+    assert_eq!(geocode(&app, "Peking").await[0]["geoname_id"], 1816670);
+    assert_eq!(geocode(&app, "Haidian Qu").await[0]["geoname_id"], 2037013);
+    assert_eq!(geocode(&app, "苏州").await.as_array().unwrap().len(), 1);
+    let suzhou = geocode(&app, "Suzhou, cn").await;
+    assert_eq!(suzhou.as_array().unwrap().len(), 2);
+    assert_eq!(suzhou[0]["geoname_id"], 1886760);
+    assert_eq!(geocode(&app, "Suzhou, US").await, json!([]));
+    let suzhou = geocode(&app, "Suzhou, Anhui").await;
+    assert_eq!(suzhou.as_array().unwrap().len(), 1);
+    assert_eq!(suzhou[0]["geoname_id"], 1795270);
+    let suzhou = geocode(&app, "suzhou，jiangsu, CN").await;
+    assert_eq!(suzhou.as_array().unwrap().len(), 1);
+    assert_eq!(suzhou[0]["geoname_id"], 1886760);
+}
+
+#[tokio::test]
+async fn update_weather_geocodes_city_name_without_location() {
+    let app = spawn_app_with_gazetteer().await;
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .expect(1)
+        .mount(&app.weather_server)
+        .await;
+
+    let response = app
+        .post_update_weather(&json!({"token": app.test_user.token, "city_name": "北京"}))
+        .await;
+
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "SUCCESS_UPDATE");
+    let location =
+        sqlx::query!("SELECT name, latitude, longitude, timezone, country FROM locations")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(location.name, "北京");
+    // Snapped to four decimals like every stored coordinate.
+    assert_eq!(
+        (location.latitude, location.longitude),
+        (39.9075, 116.3972)
+    );
+    assert_eq!(location.timezone.as_deref(), Some("Asia/Shanghai"));
+    assert_eq!(location.country.as_deref(), Some("CN"));
+}
+
+#[tokio::test]
+async fn ambiguous_city_names_list_the_candidates() {
+    let app = spawn_app_with_gazetteer().await;
+
+    let response = app
+        .post_update_weather(&json!({"token": app.test_user.token, "city_name": "Suzhou"}))
+        .await;
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "GEOCODE_AMBIGUOUS");
+    let content = body["content"].as_str().unwrap();
+    assert!(content.contains("Suzhou (Jiangsu, CN, 31.3041,120.5954)"));
+    assert!(content.contains(r#""Suzhou, Jiangsu""#));
+
+    let response = app
+        .api_client
+        .post(format!("{}/api/v1/locations", app.address))
+        .bearer_auth(&app.test_user.token)
+        .json(&json!({"name": "Suzhou"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["candidates"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn update_weather_names_coordinates_after_nearest_place() {
+    let app = spawn_app_with_gazetteer().await;
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .expect(2)
+        .mount(&app.weather_server)
+        .await;
+
+    for (location, city_name) in [("39.9042, 116.4074", "my office"), ("0.5, -30.0", "at sea")] {
+        app.post_update_weather(&json!({
+            "token": app.test_user.token,
+            "location": location,
+            "city_name": city_name
+        }))
+        .await;
+    }
+
+    let locations = sqlx::query!(
+        "SELECT name, country, admin_region, timezone FROM locations ORDER BY latitude DESC"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(locations[0].name, "北京");
+    assert_eq!(locations[0].country.as_deref(), Some("CN"));
+    assert_eq!(locations[0].admin_region.as_deref(), Some("Beijing"));
+    assert_eq!(locations[0].timezone.as_deref(), Some("Asia/Shanghai"));
+    assert_eq!(locations[1].name, "at sea");
+    assert_eq!(locations[1].country, None);
+    let city_names =
+        sqlx::query_scalar!("SELECT DISTINCT city_name FROM weather_info WHERE latitude = 39.9042")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(city_names, vec![Some("北京".to_string())]);
+}
+
+#[tokio::test]
+async fn reverse_geocoding_finds_places_across_the_antimeridian() {
+    let app = spawn_app_with_gazetteer().await;
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .expect(1)
+        .mount(&app.weather_server)
+        .await;
+
+    app.post_update_weather(&json!({
+        "token": app.test_user.token,
+        "location": "-16.79, 179.99",
+        "city_name": "taveuni"
+    }))
+    .await;
+
+    let location = sqlx::query!("SELECT name, country, timezone FROM locations")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(location.name, "Waiyevo");
+    assert_eq!(location.country.as_deref(), Some("FJ"));
+    assert_eq!(location.timezone.as_deref(), Some("Pacific/Fiji"));
+}
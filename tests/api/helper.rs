@@ -5,9 +5,12 @@ use serde_json::json;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use uuid::Uuid;
 use weather_forecast_wechat_bot::{
-    configuration::{get_configuration, DatabaseSettings},
+    configuration::{get_configuration, DatabaseSettings, Settings},
+    scheduler::Scheduler,
     start_up::{get_connection_pool, Application},
+    weather_client::WeatherClient,
 };
+use wiremock::MockServer;
 
 pub struct TestApp {
     pub address: String,
@@ -15,6 +18,9 @@ pub struct TestApp {
     pub db_pool: PgPool,
     pub test_user: TestUser,
     pub api_client: Client,
+    pub weather_server: MockServer,
+    pub weather_client: WeatherClient,
+    pub scheduler: Scheduler,
 }
 
 impl TestApp {
@@ -30,6 +36,18 @@ impl TestApp {
             .expect("Failed to execute request")
     }
 
+    pub async fn post_update_weather<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: Serialize,
+    {
+        self.api_client
+            .post(format!("{}/update_weather", self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
     pub async fn get_login_html(&self) -> String {
         self.api_client
             .get(format!("{}/login", self.address))
@@ -43,10 +61,24 @@ impl TestApp {
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+pub async fn spawn_app_with<F>(customize: F) -> TestApp
+where
+    F: FnOnce(&mut Settings),
+{
+    let weather_server = MockServer::start().await;
     let configuration = {
         let mut c = get_configuration().expect("Failed to read configuration.");
         c.database.database_name = Uuid::new_v4().to_string();
         c.application.port = 0;
+        // Every request reaches the mock provider unless a test opts in.
+        c.forecasts.cache_ttl_seconds = 0;
+        customize(&mut c);
+        for provider in c.weather_client.providers.iter_mut() {
+            provider.base_url = weather_server.uri();
+        }
         c
     };
     configure_database(&configuration.database).await;
@@ -55,7 +87,7 @@ pub async fn spawn_app() -> TestApp {
         .await
         .expect("Failed to build application.");
     let application_port = application.port();
-    let _ = tokio::spawn(application.run_until_stopped());
+    tokio::spawn(application.run_until_stopped());
 
     let client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::none())
@@ -66,9 +98,16 @@ pub async fn spawn_app() -> TestApp {
     let test_app = TestApp {
         address: format!("http://localhost:{}", application_port),
         _port: application_port,
+        weather_client: configuration
+            .weather_client
+            .clone()
+            .client()
+            .expect("Failed to build weather client."),
+        scheduler: Scheduler::build(configuration.clone()).expect("Failed to build scheduler."),
         db_pool: get_connection_pool(configuration.database),
         test_user: TestUser::generate(),
         api_client: client,
+        weather_server,
     };
     test_app.test_user.store(&test_app.db_pool).await;
     test_app
@@ -97,6 +136,7 @@ pub struct TestUser {
     pub user_id: Uuid,
     pub username: String,
     pub password: String,
+    pub token: String,
 }
 
 impl TestUser {
@@ -105,6 +145,7 @@ impl TestUser {
             user_id: Uuid::new_v4(),
             username: Uuid::new_v4().to_string(),
             password: "knysfh".to_owned(),
+            token: Uuid::new_v4().to_string(),
         }
     }
 
@@ -128,6 +169,14 @@ impl TestUser {
         .execute(pool)
         .await
         .expect("Failed to store test user.");
+        sqlx::query!(
+            "INSERT INTO tokens (user_id, token) VALUES ($1, $2)",
+            self.user_id,
+            self.token
+        )
+        .execute(pool)
+        .await
+        .expect("Failed to store test user token.");
     }
 
     pub async fn login(&self, app: &TestApp) -> reqwest::Response {
@@ -0,0 +1,210 @@
+use serde_json::{json, Value};
+use wiremock::{matchers::path, Mock, ResponseTemplate};
+
+use crate::{
+    helper::{spawn_app, TestUser},
+    update_weather::tomorrow_io_forecast,
+};
+
+#[tokio::test]
+async fn locations_support_create_read_update_and_delete() {
+    let app = spawn_app().await;
+    let locations_url = format!("{}/api/v1/locations", app.address);
+
+    let response = app
+        .api_client
+        .post(&locations_url)
+        .bearer_auth(&app.test_user.token)
+        .json(&json!({
+            "name": "Beijing",
+            "location": "39.9042, 116.4074",
+            "timezone": "Asia/Shanghai",
+            "aliases": ["北京", "Peking"]
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    let created: Value = response.json().await.unwrap();
+    let location_url = format!("{}/{}", locations_url, created["id"].as_str().unwrap());
+    assert_eq!(created["timezone"], "Asia/Shanghai");
+    assert_eq!(created["aliases"], json!(["北京", "Peking"]));
+
+    let response = app
+        .api_client
+        .put(&location_url)
+        .bearer_auth(&app.test_user.token)
+        .json(&json!({"name": "Beijing Shi", "country": "CN"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let listed: Value = app
+        .api_client
+        .get(&locations_url)
+        .bearer_auth(&app.test_user.token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(listed[0]["name"], "Beijing Shi");
+    assert_eq!(listed[0]["country"], "CN");
+    assert_eq!(listed[0]["timezone"], "Asia/Shanghai");
+
+    let response = app
+        .api_client
+        .delete(&location_url)
+        .bearer_auth(&app.test_user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app
+        .api_client
+        .get(&location_url)
+        .bearer_auth(&app.test_user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn only_the_creator_can_edit_a_shared_location() {
+    let app = spawn_app().await;
+    let locations_url = format!("{}/api/v1/locations", app.address);
+    let other_user = TestUser::generate();
+    other_user.store(&app.db_pool).await;
+
+    let response = app
+        .api_client
+        .post(&locations_url)
+        .bearer_auth(&app.test_user.token)
+        .json(&json!({"name": "Beijing", "location": "39.9042, 116.4074"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    let created: Value = response.json().await.unwrap();
+    let location_url = format!("{}/{}", locations_url, created["id"].as_str().unwrap());
+
+    // Subscribing to the same coordinate does not overwrite its name.
+    let response = app
+        .api_client
+        .post(&locations_url)
+        .bearer_auth(&other_user.token)
+        .json(&json!({"name": "Peking", "location": "39.9042, 116.4074"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    let subscribed: Value = response.json().await.unwrap();
+    assert_eq!(subscribed["id"], created["id"]);
+    assert_eq!(subscribed["name"], "Beijing");
+
+    let response = app
+        .api_client
+        .put(&location_url)
+        .bearer_auth(&other_user.token)
+        .json(&json!({"name": "Peking"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .api_client
+        .put(&location_url)
+        .bearer_auth(&app.test_user.token)
+        .json(&json!({"name": "Beijing Shi"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn locations_reject_missing_token_and_unknown_timezone() {
+    let app = spawn_app().await;
+    let locations_url = format!("{}/api/v1/locations", app.address);
+
+    let response = app.api_client.get(&locations_url).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .api_client
+        .post(&locations_url)
+        .bearer_auth(&app.test_user.token)
+        .json(&json!({
+            "name": "Beijing",
+            "location": "39.9042, 116.4074",
+            "timezone": "Mars/Olympus_Mons"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+    let stored = sqlx::query_scalar!("SELECT COUNT(*) FROM locations")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(stored, Some(0));
+}
+
+#[tokio::test]
+async fn update_weather_links_forecasts_to_locations() {
+    let app = spawn_app().await;
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .expect(2)
+        .mount(&app.weather_server)
+        .await;
+
+    app.post_update_weather(&json!({
+        "token": app.test_user.token,
+        "location": "39.9042, 116.4074",
+        "city_name": "Beijing"
+    }))
+    .await;
+    let location = sqlx::query!("SELECT id, name FROM locations")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(location.name, "Beijing");
+    sqlx::query!(
+        "UPDATE locations SET name = 'Peking' WHERE id = $1",
+        location.id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let response = app
+        .post_update_weather(&json!({
+            "token": app.test_user.token,
+            "location_id": location.id
+        }))
+        .await;
+
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "SUCCESS_UPDATE");
+    let linked = sqlx::query!(
+        "SELECT COUNT(*) AS total, COUNT(*) FILTER (WHERE city_name = 'Peking') AS renamed FROM weather_info WHERE location_id = $1",
+        location.id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(linked.total, Some(4));
+    assert_eq!(linked.renamed, Some(2));
+    let subscribed = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM user_location_subscriptions WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(subscribed, Some(1));
+}
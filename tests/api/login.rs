@@ -24,11 +24,11 @@ async fn successed_login_with_session_id_cookie() {
     let response = app.test_user.login(&app).await;
 
     assert_eq!(response.status().as_u16(), 303);
-    assert_is_redirect_to(&response, "/login");
+    assert_is_redirect_to(&response, "/admin/dashboard");
 
     let cookie_header = response
         .headers()
         .get(header::SET_COOKIE)
         .expect("Failed to parse cookie.");
-    assert!(cookie_header.to_str().unwrap().contains("session_id"));
+    assert!(cookie_header.to_str().unwrap().contains("id="));
 }
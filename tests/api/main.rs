@@ -1,2 +1,12 @@
+mod coordinates;
+mod forecast_history;
+mod forecasts;
+mod geocoding;
 mod helper;
-mod login;
\ No newline at end of file
+mod locations;
+mod login;
+mod reprocess;
+mod scheduler;
+mod timezones;
+mod update_weather;
+mod verification;
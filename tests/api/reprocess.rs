@@ -0,0 +1,73 @@
+use serde_json::json;
+use weather_forecast_wechat_bot::routers::{reprocess_raw_forecasts, ForecastFilter};
+use wiremock::{matchers::path, Mock, ResponseTemplate};
+
+use crate::{helper::spawn_app, update_weather::tomorrow_io_forecast};
+
+#[tokio::test]
+async fn reprocess_restores_weather_info_from_raw_archive() {
+    let app = spawn_app().await;
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .expect(1)
+        .mount(&app.weather_server)
+        .await;
+    app.post_update_weather(&json!({
+        "token": app.test_user.token,
+        "location": "39.9042, 116.4074",
+        "city_name": "Beijing"
+    }))
+    .await;
+    sqlx::query!("DELETE FROM weather_info")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let other_location = ForecastFilter::parse("", "", "31.2304,121.4737").unwrap();
+    let summary = reprocess_raw_forecasts(None, &other_location, &app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(summary.processed, 0);
+
+    let summary = reprocess_raw_forecasts(
+        Some(uuid::Uuid::new_v4()),
+        &ForecastFilter::default(),
+        &app.db_pool,
+    )
+    .await
+    .unwrap();
+    assert_eq!(summary.processed, 0);
+
+    let summary = reprocess_raw_forecasts(
+        Some(app.test_user.user_id),
+        &ForecastFilter::default(),
+        &app.db_pool,
+    )
+    .await
+    .unwrap();
+    assert_eq!(summary.processed, 1);
+    assert_eq!(summary.failed, 0);
+    let saved = sqlx::query!("SELECT city_name, provider FROM weather_info")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 2);
+    assert_eq!(saved[0].city_name.as_deref(), Some("Beijing"));
+    assert_eq!(saved[0].provider.as_deref(), Some("tomorrow_io"));
+}
+
+#[tokio::test]
+async fn admin_reprocess_requires_login() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/admin/reprocess", app.address))
+        .form(&json!({"from": "", "to": "", "location": ""}))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("location").unwrap(), "/login");
+}
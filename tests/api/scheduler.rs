@@ -0,0 +1,124 @@
+use serde_json::json;
+use wiremock::{matchers::path, Mock, ResponseTemplate};
+
+use crate::{
+    helper::{spawn_app_with, TestUser},
+    update_weather::tomorrow_io_forecast,
+};
+
+#[tokio::test]
+async fn scheduler_refreshes_every_tracked_location() {
+    let app = spawn_app_with(|c| {
+        c.scheduler.jitter_seconds = 0;
+        c.scheduler.max_concurrency = 1;
+    })
+    .await;
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .expect(4)
+        .mount(&app.weather_server)
+        .await;
+    Mock::given(path("/realtime"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "time": "2024-11-01T00:05:00Z",
+                "values": { "temperature": 11.0 }
+            }
+        })))
+        .expect(2)
+        .mount(&app.weather_server)
+        .await;
+    for (location, city_name) in [
+        ("39.9042, 116.4074", "Beijing"),
+        ("31.2304, 121.4737", "Shanghai"),
+    ] {
+        app.post_update_weather(&json!({
+            "token": app.test_user.token,
+            "location": location,
+            "city_name": city_name
+        }))
+        .await;
+    }
+
+    let summary = app.scheduler.run_once().await.unwrap();
+
+    assert_eq!(summary.refreshed, 2);
+    assert_eq!(summary.failed, 0);
+    let archived = sqlx::query_scalar!("SELECT COUNT(*) FROM forecast_raw")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(archived, Some(4));
+    let observed = sqlx::query_scalar!("SELECT COUNT(*) FROM observations")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(observed, Some(2));
+}
+
+#[tokio::test]
+async fn scheduler_refreshes_a_location_shared_by_users_once() {
+    let app = spawn_app_with(|c| {
+        c.scheduler.jitter_seconds = 0;
+        c.scheduler.record_observations = false;
+    })
+    .await;
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .expect(3)
+        .mount(&app.weather_server)
+        .await;
+    let other_user = TestUser::generate();
+    other_user.store(&app.db_pool).await;
+    for token in [&app.test_user.token, &other_user.token] {
+        app.post_update_weather(&json!({
+            "token": token,
+            "location": "39.9042, 116.4074",
+            "city_name": "Beijing"
+        }))
+        .await;
+    }
+
+    let summary = app.scheduler.run_once().await.unwrap();
+
+    assert_eq!(summary.refreshed, 1);
+    let issues = sqlx::query_scalar!(
+        "SELECT COUNT(DISTINCT forecast_issued_at) FROM weather_info GROUP BY user_id"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    // Each user has their own first issue and the shared scheduled one.
+    assert_eq!(issues, vec![Some(2), Some(2)]);
+}
+
+#[tokio::test]
+async fn scheduler_reuses_a_forecast_archived_within_the_cache_ttl() {
+    let app = spawn_app_with(|c| {
+        c.scheduler.jitter_seconds = 0;
+        c.scheduler.record_observations = false;
+        c.forecasts.cache_ttl_seconds = 600;
+    })
+    .await;
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tomorrow_io_forecast()))
+        .expect(1)
+        .mount(&app.weather_server)
+        .await;
+    app.post_update_weather(&json!({
+        "token": app.test_user.token,
+        "location": "39.9042, 116.4074",
+        "city_name": "Beijing"
+    }))
+    .await;
+
+    let summary = app.scheduler.run_once().await.unwrap();
+
+    assert_eq!(summary.refreshed, 1);
+    assert_eq!(summary.failed, 0);
+    let archived = sqlx::query_scalar!("SELECT COUNT(*) FROM forecast_raw")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(archived, Some(1));
+}
@@ -0,0 +1,88 @@
+use chrono::{NaiveDate, TimeDelta};
+use chrono_tz::Tz;
+use weather_forecast_wechat_bot::{
+    geocoding::{local_day_bounds, timezone_at},
+    weather_client::Coordinate,
+};
+
+use crate::{
+    forecasts::{get_forecasts, store_beijing_forecast},
+    helper::spawn_app,
+};
+
+#[test]
+fn timezones_are_looked_up_offline_and_days_follow_dst() {
+    for (latitude, longitude, timezone) in [
+        (39.9042, 116.4074, Tz::Asia__Shanghai),
+        (40.7128, -74.006, Tz::America__New_York),
+        (51.5074, -0.1278, Tz::Europe__London),
+    ] {
+        let coordinate = Coordinate::wgs84(latitude, longitude);
+        assert_eq!(timezone_at(&coordinate), Some(timezone));
+    }
+
+    let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    let (start, end) = local_day_bounds(day(2024, 11, 1), Tz::Asia__Shanghai);
+    assert_eq!(start.to_rfc3339(), "2024-10-31T16:00:00+00:00");
+    assert_eq!(end - start, TimeDelta::hours(24));
+    // New York falls back on 3 November and springs forward on 10 March.
+    let (start, end) = local_day_bounds(day(2024, 11, 3), Tz::America__New_York);
+    assert_eq!(start.to_rfc3339(), "2024-11-03T04:00:00+00:00");
+    assert_eq!(end - start, TimeDelta::hours(25));
+    let (start, end) = local_day_bounds(day(2024, 3, 10), Tz::America__New_York);
+    assert_eq!(end - start, TimeDelta::hours(23));
+}
+
+#[tokio::test]
+async fn forecasts_render_local_times_of_the_location_timezone() {
+    let app = spawn_app().await;
+    let location_id = store_beijing_forecast(&app).await;
+
+    let (status, body) = get_forecasts(&app, &format!("location_id={}", location_id)).await;
+    assert_eq!(status, 200, "{}", body);
+    let row = &body["data"][0];
+    assert_eq!(row["timezone"], "Asia/Shanghai");
+    assert_eq!(row["forecast_time"], "2024-11-01T00:00:00Z");
+    assert_eq!(row["forecast_time_local"], "2024-11-01T08:00:00+08:00");
+
+    let (_, body) = get_forecasts(
+        &app,
+        &format!("location_id={}&date=2024-11-01", location_id),
+    )
+    .await;
+    assert_eq!(body["pagination"]["total"], 2);
+    let (_, body) = get_forecasts(
+        &app,
+        &format!("location_id={}&date=2024-10-31", location_id),
+    )
+    .await;
+    assert_eq!(body["pagination"]["total"], 0);
+
+    // A timezone set on the location wins over the looked up one.
+    sqlx::query!("UPDATE locations SET timezone = 'America/New_York'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let (_, body) = get_forecasts(
+        &app,
+        &format!("location_id={}&date=2024-10-31", location_id),
+    )
+    .await;
+    assert_eq!(body["pagination"]["total"], 2);
+    assert_eq!(
+        body["data"][0]["forecast_time_local"],
+        "2024-10-31T20:00:00-04:00"
+    );
+
+    for query in [
+        "date=2024-11-01".to_string(),
+        format!(
+            "location_id={}&date=2024-11-01&from=2024-11-01T00:00",
+            location_id
+        ),
+        format!("location_id={}&date=01/11/2024", location_id),
+    ] {
+        let (status, _) = get_forecasts(&app, &query).await;
+        assert_eq!(status, 400, "{}", query);
+    }
+}
@@ -0,0 +1,152 @@
+use chrono::{Duration, DurationRound, SecondsFormat, Utc};
+use serde_json::json;
+use weather_forecast_wechat_bot::routers::{record_observations, verify_forecasts, ForecastFilter};
+use wiremock::{matchers::path, Mock, ResponseTemplate};
+
+use crate::helper::{spawn_app, TestUser};
+
+#[tokio::test]
+async fn verification_compares_forecasts_with_observations() {
+    let app = spawn_app().await;
+    let forecast_hour = Utc::now().duration_trunc(Duration::hours(1)).unwrap() + Duration::hours(2);
+    let time =
+        |offset: Duration| (forecast_hour + offset).to_rfc3339_opts(SecondsFormat::Secs, true);
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "timelines": {
+                "hourly": [
+                    {
+                        "time": time(Duration::zero()),
+                        "values": { "temperature": 12.5, "humidity": 45.0, "windSpeed": 3.0 }
+                    },
+                    {
+                        "time": time(Duration::hours(1)),
+                        "values": { "temperature": 12.0, "humidity": 50.0 }
+                    }
+                ]
+            }
+        })))
+        .expect(1)
+        .mount(&app.weather_server)
+        .await;
+    Mock::given(path("/realtime"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "time": time(Duration::minutes(10)),
+                "values": { "temperature": 11.0, "humidity": 50.0 }
+            },
+            "location": { "lat": 39.9042, "lon": 116.4074 }
+        })))
+        .expect(1)
+        .mount(&app.weather_server)
+        .await;
+    app.post_update_weather(&json!({
+        "token": app.test_user.token,
+        "location": "39.9042, 116.4074",
+        "city_name": "Beijing"
+    }))
+    .await;
+
+    let summary = record_observations(&app.weather_client, &app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(summary.recorded, 1);
+    let stats = verify_forecasts(&ForecastFilter::default(), &app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[0].provider, "tomorrow_io");
+    assert_eq!(stats[0].variable, "humidity");
+    assert_eq!(stats[0].bias, -5.0);
+    assert_eq!(stats[1].variable, "temperature");
+    assert_eq!(stats[1].samples, 1);
+    assert_eq!(stats[1].bias, 1.5);
+    assert_eq!(stats[1].mae, 1.5);
+    assert_eq!(stats[1].rmse, 1.5);
+    assert!((1..=2).contains(&stats[1].lead_hours));
+
+    // Forecasts are only compared with observations in the same unit system.
+    sqlx::query!("UPDATE observations SET units = 'imperial'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let stats = verify_forecasts(&ForecastFilter::default(), &app.db_pool)
+        .await
+        .unwrap();
+    assert!(stats.is_empty());
+}
+
+#[tokio::test]
+async fn verification_counts_a_forecast_once_and_only_against_its_provider() {
+    let app = spawn_app().await;
+    let forecast_hour = Utc::now().duration_trunc(Duration::hours(1)).unwrap() + Duration::hours(2);
+    let time =
+        |offset: Duration| (forecast_hour + offset).to_rfc3339_opts(SecondsFormat::Secs, true);
+    Mock::given(path("/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "timelines": {
+                "hourly": [
+                    { "time": time(Duration::zero()), "values": { "temperature": 12.5 } }
+                ]
+            }
+        })))
+        .expect(1)
+        .mount(&app.weather_server)
+        .await;
+    Mock::given(path("/realtime"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "time": time(Duration::minutes(10)),
+                "values": { "temperature": 11.0 }
+            },
+            "location": { "lat": 39.9042, "lon": 116.4074 }
+        })))
+        .expect(1)
+        .mount(&app.weather_server)
+        .await;
+    app.post_update_weather(&json!({
+        "token": app.test_user.token,
+        "location": "39.9042, 116.4074",
+        "city_name": "Beijing"
+    }))
+    .await;
+    record_observations(&app.weather_client, &app.db_pool)
+        .await
+        .unwrap();
+    // Another user holding a copy of the same issue, and another provider's
+    // observation of the same hour.
+    let other_user = TestUser::generate();
+    other_user.store(&app.db_pool).await;
+    sqlx::query!(
+        r#"
+        INSERT INTO weather_info
+            (id, user_id, latitude, longitude, city_name, provider, forecast_time, temperature, forecast_issued_at)
+            SELECT gen_random_uuid(), $1, latitude, longitude, city_name, provider, forecast_time, temperature, forecast_issued_at
+            FROM weather_info
+        "#,
+        other_user.user_id,
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"
+        INSERT INTO observations (id, latitude, longitude, provider, temperature, observed_at)
+            SELECT gen_random_uuid(), latitude, longitude, 'open_meteo', 30.0, observed_at
+            FROM observations
+        "#,
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let stats = verify_forecasts(&ForecastFilter::default(), &app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].provider, "tomorrow_io");
+    assert_eq!(stats[0].samples, 1);
+    assert_eq!(stats[0].bias, 1.5);
+}